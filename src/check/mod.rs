@@ -0,0 +1,308 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks of the config and notifiers, for `blooming check` and `blooming notify-test`.
+
+use std::fmt;
+
+use anyhow::bail;
+use chrono::Local;
+use reqwest::Client;
+use reqwest::Url;
+
+use crate::redact_url;
+use crate::source::try_create_sources;
+use crate::source::Item;
+use crate::state::StateStore;
+use crate::Config;
+use crate::DryRunNotifier;
+use crate::Notifier;
+use crate::QQGuildNotifier;
+use crate::QQNotifier;
+use crate::Result;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// Works, but probably not as expected.
+    Warn,
+    Fail,
+}
+
+/// The outcome of checking a component.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub status: Status,
+    /// Eg. `source mikan`, `notifier qq`.
+    pub component: String,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub entries: Vec<Entry>,
+}
+
+impl Report {
+    pub fn push(
+        &mut self,
+        status: Status,
+        component: impl Into<String>,
+        message: impl Into<String>,
+    ) {
+        self.entries.push(Entry {
+            status,
+            component: component.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn extend(&mut self, other: Report) {
+        self.entries.extend(other.entries);
+    }
+
+    pub fn failed(&self) -> bool {
+        self.entries.iter().any(|e| e.status == Status::Fail)
+    }
+
+    /// 0 if nothing fails, 1 otherwise.
+    pub fn exit_code(&self) -> i32 {
+        if self.failed() { 1 } else { 0 }
+    }
+
+    fn check(&mut self, component: &str, result: Result<String>) {
+        match result {
+            Ok(message) => self.push(Status::Ok, component, message),
+            Err(e) => self.push(Status::Fail, component, e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            let status = match entry.status {
+                Status::Ok => "ok",
+                Status::Warn => "warn",
+                Status::Fail => "FAIL",
+            };
+            writeln!(f, "{:<4}  {}: {}", status, entry.component, entry.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the config without any request.
+pub fn check_config(config: &Config) -> Report {
+    let mut report = Report::default();
+
+    for (name, source) in try_create_sources(config) {
+        let component = format!("source {name}");
+        let source = match source {
+            Ok(source) => source,
+            Err(e) => {
+                report.push(Status::Fail, component, e.to_string());
+                continue;
+            }
+        };
+        let urls = source.rsses();
+        let invalid = urls
+            .iter()
+            .filter_map(|url| check_url(url).err())
+            .collect::<Vec<_>>();
+        if urls.is_empty() {
+            report.push(Status::Warn, component, "no feeds to pull");
+        } else if !invalid.is_empty() {
+            for e in invalid {
+                report.push(Status::Fail, &component, e.to_string());
+            }
+        } else {
+            report.push(Status::Ok, component, format!("{} feed(s)", urls.len()));
+        }
+    }
+
+    if let Some(qq) = &config.qq {
+        if let Err(e) = check_url(&qq.api) {
+            report.push(Status::Fail, "notifier qq", e.to_string());
+        } else if qq.dms.is_empty() && qq.groups.is_empty() {
+            report.push(
+                Status::Warn,
+                "notifier qq",
+                "no `dms` or `groups` to notify",
+            );
+        } else {
+            let message = format!("{} contacts, {} groups", qq.dms.len(), qq.groups.len());
+            report.push(Status::Ok, "notifier qq", message);
+        }
+    }
+    if let Some(qq_guild) = &config.qq_guild {
+        let missing = [
+            ("app_id", qq_guild.app_id.as_str()),
+            ("app_secret", qq_guild.app_secret.expose()),
+            ("channel_id", qq_guild.channel_id.as_str()),
+        ]
+        .into_iter()
+        .filter(|(_, value)| value.is_empty())
+        .map(|(key, _)| format!("`qq_guild.{key}`"))
+        .collect::<Vec<_>>();
+        if missing.is_empty() {
+            report.push(Status::Ok, "notifier qq_guild", "configured");
+        } else {
+            report.push(
+                Status::Fail,
+                "notifier qq_guild",
+                format!("{} not set", missing.join(", ")),
+            );
+        }
+    }
+    if config.qq.is_none() && config.qq_guild.is_none() {
+        report.push(
+            Status::Warn,
+            "notifier",
+            "no notifiers, nothing will be sent",
+        );
+    }
+
+    if let Some(path) = &config.state_file {
+        let result = StateStore::open(Some(path)).map(|_| path.clone());
+        report.check("state_file", result);
+    }
+
+    report
+}
+
+/// Pull each feed once and verify the credentials of the notifiers.
+pub async fn check_live(config: &Config) -> Report {
+    let mut report = Report::default();
+
+    let sources = try_create_sources(config)
+        .into_iter()
+        .filter_map(|(name, source)| Some((name, source.ok()?)))
+        .collect::<Vec<_>>();
+    let pulls =
+        futures::future::join_all(sources.iter().map(|(_, source)| source.pull_items())).await;
+    for ((name, source), result) in sources.iter().zip(pulls) {
+        let component = format!("source {name}");
+        let mut failed = false;
+        for status in source.feeds().status() {
+            let component = format!("{component} feed '{}'", status.feed.name());
+            match status.last_error {
+                Some(e) => {
+                    failed = true;
+                    report.push(Status::Fail, component, e);
+                }
+                None if status.last_success.is_some() => {
+                    report.push(Status::Ok, component, format!("{} items", status.items))
+                }
+                // Not pulled, eg. rate limited.
+                None => report.push(Status::Warn, component, "not pulled"),
+            }
+        }
+        // Errors of the feeds are reported above.
+        if let (Err(e), false) = (result, failed) {
+            report.push(Status::Fail, component, e.to_string());
+        }
+    }
+
+    let client = Client::new();
+    if let Some(qq) = &config.qq {
+        let notifier = QQNotifier::new(client.clone(), qq.clone());
+        report.check("notifier qq", notifier.check().await);
+    }
+    if let Some(qq_guild) = &config.qq_guild {
+        let notifier = QQGuildNotifier::new(client, qq_guild.clone());
+        report.check("notifier qq_guild", notifier.check().await);
+    }
+
+    report
+}
+
+/// Send a synthetic item through `notifier`, or each configured notifier if it
+/// is `None`, and report the outcome of each target.
+pub async fn notify_test(config: &Config, notifier: Option<&str>, dry_run: bool) -> Result<Report> {
+    let configured = [
+        ("qq", config.qq.is_some()),
+        ("qq_guild", config.qq_guild.is_some()),
+    ]
+    .into_iter()
+    .filter(|(_, configured)| *configured)
+    .map(|(name, _)| name)
+    .collect::<Vec<_>>();
+    let names = match notifier {
+        Some(name) if configured.contains(&name) => vec![name],
+        Some(name) => bail!(
+            "Notifier '{}' is not configured, configured: [{}]",
+            name,
+            configured.join(", ")
+        ),
+        None if configured.is_empty() => bail!("No notifiers configured"),
+        None => configured,
+    };
+
+    let client = Client::new();
+    let mut report = Report::default();
+    for name in names {
+        let component = format!("notifier {name}");
+        let outcomes = match name {
+            "qq" => {
+                let notifier = QQNotifier::new(client.clone(), config.qq.clone().unwrap());
+                send_test(notifier, dry_run).await
+            }
+            "qq_guild" => {
+                let notifier =
+                    QQGuildNotifier::new(client.clone(), config.qq_guild.clone().unwrap());
+                send_test(notifier, dry_run).await
+            }
+            _ => unreachable!(),
+        };
+        if outcomes.is_empty() {
+            report.push(Status::Warn, &component, "no targets to notify");
+        }
+        for (target, result) in outcomes {
+            let result = result.map(|_| "sent".to_string());
+            report.check(&format!("{component} {target}"), result);
+        }
+    }
+    Ok(report)
+}
+
+async fn send_test<T: Notifier>(mut notifier: T, dry_run: bool) -> Vec<(String, Result<()>)> {
+    let item = Item {
+        title: "[blooming] Test notification".to_string(),
+        url: "https://github.com/RinChanNOWWW/blooming".to_string(),
+        pub_date: Local::now(),
+        label: Some("notify-test".to_string()),
+        ..Default::default()
+    };
+    if dry_run {
+        let mut notifier = DryRunNotifier::new(notifier);
+        notifier.notify_test("blooming", vec![item]).await
+    } else {
+        notifier.notify_test("blooming", vec![item]).await
+    }
+}
+
+/// Only http and https feeds are supported.
+fn check_url(url: &str) -> Result<()> {
+    let parsed =
+        Url::parse(url).map_err(|e| anyhow::anyhow!("Invalid url '{}': {}", redact_url(url), e))?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(()),
+        scheme => Err(anyhow::anyhow!(
+            "Invalid url '{}': unsupported scheme '{}'",
+            redact_url(url),
+            scheme
+        )),
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use chrono::DateTime;
+use chrono::Local;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::release::ReleaseInfo;
+use crate::CollapseConfig;
+use crate::Item;
+
+/// Collapse releases of the same episode from different fansub groups.
+///
+/// Items are held for a window after the first release of an episode is seen.
+/// When the window expires, only the preferred release is emitted, with the
+/// others attached as its alternatives.
+pub struct Collapser {
+    conf: CollapseConfig,
+    window: Duration,
+    pending: Vec<Pending>,
+}
+
+/// Releases of an episode held by a [`Collapser`], saved with the state so that
+/// they survive restarts.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HeldEpisode {
+    pub first_seen: DateTime<Local>,
+    pub items: Vec<Item>,
+}
+
+struct Pending {
+    names: Vec<String>,
+    season: Option<u32>,
+    episode: u32,
+    first_seen: Instant,
+    items: Vec<Item>,
+}
+
+impl Pending {
+    fn matches(&self, names: &[String], season: Option<u32>, episode: u32) -> bool {
+        let season_matches = match (self.season, season) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        };
+        season_matches && self.episode == episode && names.iter().any(|n| self.names.contains(n))
+    }
+}
+
+impl Collapser {
+    pub fn new(conf: CollapseConfig) -> Self {
+        Self {
+            window: conf.window,
+            conf,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Hold new items and return the items to notify now.
+    ///
+    /// Items without a recognized series or episode are returned immediately.
+    pub fn collapse(&mut self, items: Vec<Item>, now: Instant) -> Vec<Item> {
+        let mut ready = items
+            .into_iter()
+            .filter_map(|item| self.hold(item, now))
+            .collect::<Vec<_>>();
+
+        let (expired, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|p| now.duration_since(p.first_seen) >= self.window);
+        self.pending = pending;
+        ready.extend(expired.into_iter().map(|p| self.pick(p.items)));
+        ready
+    }
+
+    /// The releases held now, to be saved with the state.
+    pub fn held(&self, now: Instant) -> Vec<HeldEpisode> {
+        let wall_now = Local::now();
+        self.pending
+            .iter()
+            .map(|p| HeldEpisode {
+                first_seen: chrono::Duration::from_std(now.duration_since(p.first_seen))
+                    .map_or(wall_now, |age| wall_now - age),
+                items: p.items.clone(),
+            })
+            .collect()
+    }
+
+    /// Hold the releases saved by [`Collapser::held`] again, keeping the time they
+    /// were first seen. They are emitted by the next [`Collapser::collapse`].
+    pub fn restore(&mut self, held: Vec<HeldEpisode>, now: Instant) {
+        let wall_now = Local::now();
+        for episode in held {
+            // Releases older than the window expire all the same.
+            let age = (wall_now - episode.first_seen)
+                .to_std()
+                .unwrap_or_default()
+                .min(self.window);
+            let first_seen = now.checked_sub(age).unwrap_or(now);
+            for item in episode.items {
+                self.hold(item, first_seen);
+            }
+        }
+    }
+
+    /// Hold the item with the releases of the same episode. Returns the item if it
+    /// is not a release of an episode.
+    fn hold(&mut self, item: Item, now: Instant) -> Option<Item> {
+        let Some((names, season, episode)) = item.release.as_ref().and_then(episode_key) else {
+            return Some(item);
+        };
+        match self
+            .pending
+            .iter_mut()
+            .find(|p| p.matches(&names, season, episode))
+        {
+            Some(pending) => {
+                for name in names {
+                    if !pending.names.contains(&name) {
+                        pending.names.push(name);
+                    }
+                }
+                pending.season = pending.season.or(season);
+                pending.items.push(item);
+            }
+            None => self.pending.push(Pending {
+                names,
+                season,
+                episode,
+                first_seen: now,
+                items: vec![item],
+            }),
+        }
+        None
+    }
+
+    fn pick(&self, mut items: Vec<Item>) -> Item {
+        items.sort_by_key(|item| (self.rank(item), item.pub_date));
+        let mut best = items.remove(0);
+        best.alternatives = items;
+        best
+    }
+
+    /// Lower is better. Values not in the preference lists rank last.
+    fn rank(&self, item: &Item) -> (usize, usize, usize) {
+        let release = item.release.as_ref();
+        let position = |prefs: &[String], value: Option<&String>| {
+            value
+                .and_then(|v| prefs.iter().position(|p| p.eq_ignore_ascii_case(v)))
+                .unwrap_or(prefs.len())
+        };
+        (
+            position(&self.conf.groups, release.and_then(|r| r.group.as_ref())),
+            position(
+                &self.conf.resolutions,
+                release.and_then(|r| r.resolution.as_ref()),
+            ),
+            position(
+                &self.conf.subtitles,
+                release.and_then(|r| r.subtitle.as_ref()),
+            ),
+        )
+    }
+}
+
+fn episode_key(release: &ReleaseInfo) -> Option<(Vec<String>, Option<u32>, u32)> {
+    let names = release.series_names();
+    if names.is_empty() {
+        return None;
+    }
+    Some((names, release.season, release.episode?))
+}
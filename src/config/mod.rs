@@ -12,15 +12,34 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod duration;
+mod secret;
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::bail;
 use clap::Parser;
+use log::LevelFilter;
+pub use secret::has_secrets;
+pub use secret::redact_url;
+pub use secret::Secret;
 use serde::Deserialize;
 use serde::Serialize;
-use serfig::collectors::from_file;
 
 use crate::Result;
 
+/// Sources inheriting the options of `[defaults]`.
+const SOURCES: [&str; 7] = [
+    "mikan", "byrbt", "tjupt", "nyaa", "dmhy", "acgrip", "torznab",
+];
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
-#[serde(default)]
+#[serde(default, deny_unknown_fields)]
 pub struct QQBotConfig {
     /// The name of the bot.
     pub name: String,
@@ -32,28 +51,39 @@ pub struct QQBotConfig {
     pub dms: Vec<u64>,
     /// qq groups
     pub groups: Vec<u64>,
-    /// The delay between two messages. (ms)
-    pub delay: u64,
+    /// The delay between two messages. Eg. `200ms`, integers are milliseconds.
+    #[serde(with = "duration::millis")]
+    pub delay: Duration,
     /// If the message contains torrents' URLs.
     pub with_torrent: bool,
+    /// If the message contains magnet links.
+    ///
+    /// Only available for sources with torrent parsing enabled.
+    pub with_magnet: bool,
+    /// Retry policy of sending messages.
+    pub retry: RetryConfig,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
-#[serde(default)]
+#[serde(default, deny_unknown_fields)]
 pub struct QQGuildBotConfig {
     /// The app id of the bot.
     pub app_id: String,
-    /// The app secret of the bot.
-    pub app_secret: String,
+    /// The app secret of the bot. Can be `env:NAME` or `file:PATH`.
+    pub app_secret: Secret,
     /// The channel id of the channel to notify.
     pub channel_id: String,
     /// If use sandbox API.
     pub sandbox: bool,
+    /// Retry policy of sending messages.
+    pub retry: RetryConfig,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
-#[serde(default)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
+    /// Options inherited by every source, unless the source sets them.
+    pub defaults: DefaultsConfig,
     /// config of qq bot.
     pub qq: Option<QQBotConfig>,
     /// config of qq guild bot.
@@ -64,55 +94,767 @@ pub struct Config {
     pub byrbt: Option<ByrbtConfig>,
     /// tjupt
     pub tjupt: Option<TjuptConfig>,
+    /// nyaa
+    pub nyaa: Option<NyaaConfig>,
+    /// dmhy
+    pub dmhy: Option<DmhyConfig>,
+    /// acg.rip
+    pub acgrip: Option<AcgRipConfig>,
+    /// Torznab indexers, eg. Jackett, Prowlarr.
+    pub torznab: Option<TorznabConfig>,
+    /// Collapse releases of the same episode from different groups.
+    pub collapse: Option<CollapseConfig>,
+    /// Track episodes of each series.
+    pub episodes: Option<EpisodesConfig>,
+    /// The file to persist the state. The state is kept in memory if not set.
+    pub state_file: Option<String>,
+    /// Files and log rotation of the daemon mode.
+    pub daemon: DaemonConfig,
+    /// Format and levels of the logs.
+    pub log: LogConfig,
+    /// Serve the metrics in the Prometheus text format at `/metrics`. Disabled if not set.
+    pub metrics: Option<MetricsConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// The address to listen on. Defaults to `127.0.0.1:9100`.
+    pub listen: SocketAddr,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            listen: SocketAddr::from(([127, 0, 0, 1], 9100)),
+        }
+    }
+}
+
+/// `RUST_LOG` is applied on top of it, eg. `RUST_LOG=debug` or `RUST_LOG=warn,blooming=debug`.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// `text`, or `json` for one object per line with the fields of the record,
+    /// eg. source, feed, notifier, items.
+    pub format: LogFormat,
+    /// The level of blooming. Defaults to `info`.
+    pub level: Option<LevelFilter>,
+    /// Levels by module path, the longest matching path wins. Eg. `"blooming::source" = "debug"`,
+    /// `reqwest = "info"`. Other crates default to `warn`.
+    pub modules: BTreeMap<String, LevelFilter>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Relative paths are relative to the working directory.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonConfig {
+    /// The file to write the pid of the daemon to, used by `stop`, `status` and `restart`.
+    pub pid_file: String,
+    /// The file that stdout and stderr are redirected to.
+    pub log_file: String,
+    /// Rotate the log file when it is larger than this (bytes). 0 means no limit.
+    pub log_max_size: u64,
+    /// Rotate the log file periodically.
+    pub log_rotation: LogRotation,
+    /// Number of rotated log files to keep, eg. `blooming.log.1`. The oldest are removed.
+    pub log_keep: usize,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            pid_file: "blooming.pid".to_string(),
+            log_file: "blooming.log".to_string(),
+            log_max_size: 10 * 1024 * 1024,
+            log_rotation: LogRotation::Never,
+            log_keep: 5,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    #[default]
+    Never,
+    Hourly,
+    Daily,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollapseConfig {
+    /// How long to wait for other releases after the first release of an episode is seen.
+    /// Eg. `30m`, integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub window: Duration,
+    /// Preferred fansub groups, the former the better.
+    pub groups: Vec<String>,
+    /// Preferred resolutions, the former the better. Eg. 1080P.
+    pub resolutions: Vec<String>,
+    /// Preferred subtitle languages, the former the better. Eg. 简体, CHS.
+    pub subtitles: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct EpisodesConfig {
+    /// Series to track, with the expected number of episodes to alert when a season
+    /// is complete. Other series are not tracked.
+    pub series: Vec<SeriesConfig>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeriesConfig {
+    /// Any name of the series in the release titles. Eg. Vinland Saga.
+    pub name: String,
+    /// Match any season if not set.
+    pub season: Option<u32>,
+    /// The number of episodes of the season.
+    pub episodes: u32,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Parser)]
 #[serde(default)]
 pub struct ClapConfig {
-    #[clap(long, short = 'c', required = true)]
+    #[clap(long, short = 'c', global = true, default_value = "config.toml")]
     pub config_file: String,
 
+    /// Run in the background, see the `[daemon]` section of the config for its files.
     #[clap(long, short = 'd', action, default_value_t)]
     pub daemonize: bool,
+
+    /// Run the full pipeline but print the messages instead of sending them.
+    /// The state is not saved.
+    #[clap(long, action, global = true, default_value_t)]
+    pub dry_run: bool,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
-#[serde(default)]
+#[derive(Debug, Serialize, Deserialize, clap::Subcommand)]
+pub enum Command {
+    /// Show the episodes seen of each series.
+    Episodes {
+        /// Only show the series whose name contains it.
+        series: Option<String>,
+    },
+    /// Pull a source once and print the items.
+    Fetch {
+        /// The config section of the source. Eg. mikan, nyaa.
+        source: String,
+        /// Print the items as JSON instead of a table.
+        #[clap(long, action)]
+        json: bool,
+    },
+    /// Pull the sources and notify the new items once, then exit. For cron jobs or
+    /// systemd timers, needs `state_file` to remember the items notified.
+    Once,
+    /// Check the config and exit with 0 if nothing fails, 1 if any check fails, or 2 if the
+    /// config cannot be loaded.
+    Check {
+        /// Also pull each feed once and verify the credentials of the notifiers.
+        #[clap(long, action)]
+        live: bool,
+    },
+    /// Stop the daemon started by `-d`, waiting for the in-flight notifications.
+    Stop,
+    /// Show whether the daemon is running. Exits with 0 if it is running, 1 if it died
+    /// without cleaning up the pid file, or 3 if it is not running.
+    Status,
+    /// Stop the daemon if it is running, then start it again.
+    Restart,
+    /// Send a test message through each configured notifier and report the outcome of each
+    /// target. Exits with 1 if any target fails.
+    NotifyTest {
+        /// Only test this notifier. Eg. qq, qq_guild.
+        #[clap(long)]
+        notifier: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct MikanConfig {
-    // mikan rss link.
-    pub rss: String,
+    // Base url of mikan, can be a mirror.
+    pub base_url: String,
+
+    // Personal mikan rss link (MyBangumi), needs an account. Can be `env:NAME` or `file:PATH`.
+    pub rss: Option<Secret>,
 
-    // Time interval for checking rss. (ms)
-    pub interval: u64,
+    // More rss links, eg. personal links of other accounts.
+    pub rsses: Vec<Secret>,
 
-    // Proxy address
-    pub proxy: Option<String>,
+    // Bangumis to subscribe, one rss for each.
+    pub bangumis: Vec<MikanBangumiConfig>,
+
+    // Search keywords, one rss for each.
+    pub keywords: Vec<String>,
+
+    // Subscribe the latest releases of all bangumis.
+    pub classic: bool,
+
+    // Time interval for checking rss. Eg. `5m`, integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub interval: Duration,
+
+    // Proxy address, same as `http.proxy`.
+    pub proxy: Option<Secret>,
+
+    // Download and parse the torrent files of new items.
+    pub torrent: bool,
+
+    // HTTP options.
+    pub http: HttpConfig,
+
+    // Retry policy of pulling each rss.
+    pub retry: RetryConfig,
+
+    // Schedule of pulling, `interval` is used outside of the windows.
+    pub schedule: ScheduleConfig,
+}
+
+impl Default for MikanConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://mikanani.me".to_string(),
+            rss: None,
+            rsses: vec![],
+            bangumis: vec![],
+            keywords: vec![],
+            classic: false,
+            interval: Duration::ZERO,
+            proxy: None,
+            torrent: false,
+            http: HttpConfig::default(),
+            retry: RetryConfig::default(),
+            schedule: ScheduleConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MikanBangumiConfig {
+    // Bangumi id, eg. 3141 in https://mikanani.me/Home/Bangumi/3141.
+    pub id: u32,
+
+    // Only the releases of the subgroup, eg. 370 (LoliHouse).
+    pub subgroup: Option<u32>,
+
+    // Shown in notifications. Defaults to `Bangumi <id>`.
+    pub label: Option<String>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
-#[serde(default)]
+#[serde(default, deny_unknown_fields)]
 pub struct ByrbtConfig {
-    // rss links
-    pub rsses: Vec<String>,
+    // rss links with passkeys. Can be `env:NAME` or `file:PATH`.
+    pub rsses: Vec<Secret>,
+
+    // Time interval for checking rss. Eg. `5m`, integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub interval: Duration,
+
+    // Download and parse the torrent files of new items.
+    pub torrent: bool,
+
+    // HTTP options.
+    pub http: HttpConfig,
 
-    // Time interval for checking rss. (ms)
-    pub interval: u64,
+    // Retry policy of pulling each rss.
+    pub retry: RetryConfig,
+
+    // Schedule of pulling, `interval` is used outside of the windows.
+    pub schedule: ScheduleConfig,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
-#[serde(default)]
+#[serde(default, deny_unknown_fields)]
 pub struct TjuptConfig {
-    // rss links
-    pub rsses: Vec<String>,
+    // rss links with passkeys. Can be `env:NAME` or `file:PATH`.
+    pub rsses: Vec<Secret>,
+
+    // Time interval for checking rss. Eg. `5m`, integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub interval: Duration,
+
+    // Download and parse the torrent files of new items.
+    pub torrent: bool,
+
+    // HTTP options.
+    pub http: HttpConfig,
+
+    // Retry policy of pulling each rss.
+    pub retry: RetryConfig,
+
+    // Schedule of pulling, `interval` is used outside of the windows.
+    pub schedule: ScheduleConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NyaaConfig {
+    // Base url of nyaa, can be a mirror.
+    pub base_url: String,
+
+    // Search query.
+    pub query: String,
 
-    // Time interval for checking rss. (ms)
-    pub interval: u64,
+    // Categories to search, eg. 1_2 (Anime - English-translated). Search all if empty.
+    pub categories: Vec<String>,
+
+    // 0: no filter, 1: no remakes, 2: trusted only.
+    pub filter: u8,
+
+    // Time interval for checking rss. Eg. `5m`, integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub interval: Duration,
+
+    // Download and parse the torrent files of new items.
+    pub torrent: bool,
+
+    // HTTP options.
+    pub http: HttpConfig,
+
+    // Retry policy of pulling each rss.
+    pub retry: RetryConfig,
+
+    // Schedule of pulling, `interval` is used outside of the windows.
+    pub schedule: ScheduleConfig,
+}
+
+impl Default for NyaaConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://nyaa.si".to_string(),
+            query: String::new(),
+            categories: vec![],
+            filter: 0,
+            interval: Duration::ZERO,
+            torrent: false,
+            http: HttpConfig::default(),
+            retry: RetryConfig::default(),
+            schedule: ScheduleConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DmhyConfig {
+    // Base url of dmhy, can be a mirror.
+    pub base_url: String,
+
+    // Search keywords, one rss for each. Subscribe the latest releases if empty.
+    pub keywords: Vec<String>,
+
+    // Only the releases of the team.
+    pub team_id: Option<u32>,
+
+    // Only the releases of the category, eg. 2 (動畫).
+    pub sort_id: Option<u32>,
+
+    // Time interval for checking rss. Eg. `5m`, integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub interval: Duration,
+
+    // HTTP options.
+    pub http: HttpConfig,
+
+    // Retry policy of pulling each rss.
+    pub retry: RetryConfig,
+
+    // Schedule of pulling, `interval` is used outside of the windows.
+    pub schedule: ScheduleConfig,
+}
+
+impl Default for DmhyConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://share.dmhy.org".to_string(),
+            keywords: vec![],
+            team_id: None,
+            sort_id: None,
+            interval: Duration::ZERO,
+            http: HttpConfig::default(),
+            retry: RetryConfig::default(),
+            schedule: ScheduleConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AcgRipConfig {
+    // Base url of acg.rip, can be a mirror.
+    pub base_url: String,
+
+    // Search keywords, one rss for each. Subscribe the latest releases if empty.
+    pub keywords: Vec<String>,
+
+    // Only the releases of the team. Takes precedence over `category`.
+    pub team_id: Option<u32>,
+
+    // Only the releases of the category, eg. 1 (动画).
+    pub category: Option<u32>,
+
+    // Time interval for checking rss. Eg. `5m`, integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub interval: Duration,
+
+    // Download and parse the torrent files of new items.
+    pub torrent: bool,
+
+    // HTTP options.
+    pub http: HttpConfig,
+
+    // Retry policy of pulling each rss.
+    pub retry: RetryConfig,
+
+    // Schedule of pulling, `interval` is used outside of the windows.
+    pub schedule: ScheduleConfig,
+}
+
+impl Default for AcgRipConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://acg.rip".to_string(),
+            keywords: vec![],
+            team_id: None,
+            category: None,
+            interval: Duration::ZERO,
+            torrent: false,
+            http: HttpConfig::default(),
+            retry: RetryConfig::default(),
+            schedule: ScheduleConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TorznabConfig {
+    // Torznab endpoint without `/api`.
+    // Eg. http://localhost:9117/api/v2.0/indexers/all/results/torznab (Jackett),
+    // http://localhost:9696/1 (Prowlarr).
+    pub url: String,
+
+    // Can be `env:NAME` or `file:PATH`.
+    pub apikey: Secret,
+
+    // search or tvsearch.
+    pub mode: TorznabMode,
+
+    // Search query.
+    pub query: String,
+
+    // Categories to search, eg. 5070 (TV/Anime). Search all if empty.
+    pub categories: Vec<u32>,
+
+    // Season to search, only for tvsearch.
+    pub season: Option<u32>,
+
+    // Time interval for checking rss. Eg. `5m`, integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub interval: Duration,
+
+    // Download and parse the torrent files of new items.
+    pub torrent: bool,
+
+    // HTTP options.
+    pub http: HttpConfig,
+
+    // Retry policy of pulling each rss.
+    pub retry: RetryConfig,
+
+    // Schedule of pulling, `interval` is used outside of the windows.
+    pub schedule: ScheduleConfig,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TorznabMode {
+    #[default]
+    Search,
+    TvSearch,
+}
+
+impl fmt::Display for TorznabMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TorznabMode::Search => write!(f, "search"),
+            TorznabMode::TvSearch => write!(f, "tvsearch"),
+        }
+    }
+}
+
+/// HTTP options of a source.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Proxy address. Eg. http://localhost:7890, socks5://localhost:1080.
+    pub proxy: Option<Secret>,
+    /// Timeout of each request, 30s by default. Integers are seconds.
+    #[serde(with = "duration::option_secs")]
+    pub timeout: Option<Duration>,
+    /// User agent of requests.
+    pub user_agent: Option<String>,
+    /// Extra headers of requests. Values can be `env:NAME` or `file:PATH`.
+    pub headers: BTreeMap<String, Secret>,
+    /// Cookies sent with requests. Eg. `uid=1; pass=xxx`. Can be `env:NAME` or `file:PATH`.
+    pub cookie: Option<Secret>,
+    /// Keep cookies set by the site for later requests.
+    pub cookie_store: bool,
+    /// Skip verifying TLS certificates. Only for self-signed sites.
+    pub insecure: bool,
+    /// PEM file of an extra CA certificate to trust.
+    pub ca_file: Option<String>,
+}
+
+/// Retry policy of failed requests. Only temporary errors (timeouts, 5xx) are retried.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    /// constant or exponential.
+    pub backoff: BackoffKind,
+    /// Max number of retries. 0 to disable retrying.
+    pub max_times: usize,
+    /// The delay of constant backoff, or the first delay of exponential backoff.
+    /// Eg. `1s`, integers are milliseconds.
+    #[serde(with = "duration::millis")]
+    pub delay: Duration,
+    /// Max delay of exponential backoff. Integers are milliseconds.
+    #[serde(with = "duration::millis")]
+    pub max_delay: Duration,
+    /// Randomize the delays so that retries of different tasks do not happen at the same time.
+    pub jitter: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            backoff: BackoffKind::Constant,
+            max_times: 3,
+            delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            jitter: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackoffKind {
+    #[default]
+    Constant,
+    Exponential,
+}
+
+/// Schedule of pulling a source.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScheduleConfig {
+    /// Cron expression with seconds. Eg. `0 */5 * * * *`. Overrides the other options.
+    pub cron: Option<String>,
+    /// Time windows with their own intervals. The first window containing the time is used.
+    pub windows: Vec<WindowConfig>,
+    /// Max random delay added to each pull, to avoid hitting the site at the same time.
+    /// Integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub jitter: Duration,
+    /// Pull once on startup instead of waiting for the first interval.
+    pub immediate: bool,
+    /// Adapt the interval to the feeds. Overrides the windows.
+    pub adaptive: Option<AdaptiveConfig>,
+}
+
+/// Follow the publish rate of the feeds, ie. the average gap between their items,
+/// and back off when the feeds stay quiet longer than that.
+///
+/// Never pulls faster than the `ttl` of the feeds, and skips the `skipHours`
+/// and `skipDays` of the feeds.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdaptiveConfig {
+    /// Min time interval. Integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub min: Duration,
+    /// Max time interval. Integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub max: Duration,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        Self {
+            min: Duration::from_secs(60),
+            max: Duration::from_secs(3600),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    /// Days of the week. Eg. `["Sat", "Sun"]`. Every day if empty.
+    pub days: Vec<String>,
+    /// Start time. Eg. `18:00`.
+    pub start: String,
+    /// End time. Eg. `23:30`. Windows can cross midnight, eg. `22:00` to `02:00`.
+    pub end: String,
+    /// Time interval for checking rss in the window. Integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub interval: Duration,
+}
+
+/// Options shared by the sources.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DefaultsConfig {
+    /// Time interval for checking rss. Eg. `5m`, integers are seconds.
+    #[serde(with = "duration::secs")]
+    pub interval: Duration,
+    /// HTTP options.
+    pub http: HttpConfig,
+    /// Retry policy of pulling each rss.
+    pub retry: RetryConfig,
+    /// Schedule of pulling.
+    pub schedule: ScheduleConfig,
 }
 
 impl Config {
     pub fn load(file: &str) -> Result<Self> {
-        let mut builder = serfig::Builder::default();
-        builder = builder.collect(from_file(serfig::parsers::Toml, file));
-        builder.build()
+        let content = std::fs::read_to_string(file)
+            .map_err(|e| anyhow!("Read config file '{}' failed: {}", file, e))?;
+        Self::parse(&content).map_err(|e| anyhow!("Invalid config file '{}': {}", file, e))
+    }
+
+    /// Parse the config from toml.
+    ///
+    /// Sources inherit the options of `[defaults]` they do not set. Tables are
+    /// merged key by key, eg. a source setting `http.proxy` still inherits `http.timeout`.
+    pub fn parse(content: &str) -> Result<Self> {
+        // Check the keys against the file first, so that errors point to the lines.
+        let _: Config = toml::from_str(content)?;
+
+        let mut value: toml::Value = toml::from_str(content)?;
+        if let Some(table) = value.as_table_mut() {
+            if let Some(defaults) = table.get("defaults").cloned() {
+                for name in SOURCES {
+                    if let Some(source) = table.get_mut(name) {
+                        inherit(source, &defaults);
+                    }
+                }
+            }
+        }
+
+        let config: Config = value.try_into()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Top-level sections changed from `old`, including the added and removed ones.
+    ///
+    /// `defaults` is not included, compare the sources inheriting it instead.
+    pub fn changed_sections(&self, old: &Config) -> BTreeSet<String> {
+        let new = self.sections();
+        let old = old.sections();
+        new.keys()
+            .chain(old.keys())
+            .filter(|key| *key != "defaults" && new.get(*key) != old.get(*key))
+            .cloned()
+            .collect()
+    }
+
+    fn sections(&self) -> serde_json::Map<String, serde_json::Value> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(sections)) => sections,
+            _ => unreachable!("config is serialized as a map"),
+        }
+    }
+
+    /// Reject the options that would make a source pull in a tight loop.
+    fn validate(&self) -> Result<()> {
+        let sources = [
+            (
+                "mikan",
+                self.mikan.as_ref().map(|c| (c.interval, &c.schedule)),
+            ),
+            (
+                "byrbt",
+                self.byrbt.as_ref().map(|c| (c.interval, &c.schedule)),
+            ),
+            (
+                "tjupt",
+                self.tjupt.as_ref().map(|c| (c.interval, &c.schedule)),
+            ),
+            (
+                "nyaa",
+                self.nyaa.as_ref().map(|c| (c.interval, &c.schedule)),
+            ),
+            (
+                "dmhy",
+                self.dmhy.as_ref().map(|c| (c.interval, &c.schedule)),
+            ),
+            (
+                "acgrip",
+                self.acgrip.as_ref().map(|c| (c.interval, &c.schedule)),
+            ),
+            (
+                "torznab",
+                self.torznab.as_ref().map(|c| (c.interval, &c.schedule)),
+            ),
+        ];
+        for (name, source) in sources {
+            let Some((interval, schedule)) = source else {
+                continue;
+            };
+            // The interval is not used with cron.
+            if interval.is_zero() && schedule.cron.is_none() {
+                bail!(
+                    "`{}.interval` must be greater than 0, set it or `defaults.interval`",
+                    name
+                );
+            }
+            if schedule.windows.iter().any(|w| w.interval.is_zero()) {
+                bail!(
+                    "`{}.schedule.windows` must have intervals greater than 0",
+                    name
+                );
+            }
+            if let Some(adaptive) = &schedule.adaptive {
+                if adaptive.min.is_zero() {
+                    bail!("`{}.schedule.adaptive.min` must be greater than 0", name);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fill the keys of `value` missing from `defaults`, recursively for tables.
+fn inherit(value: &mut toml::Value, defaults: &toml::Value) {
+    let (Some(table), Some(defaults)) = (value.as_table_mut(), defaults.as_table()) else {
+        return;
+    };
+    for (key, default) in defaults {
+        match table.get_mut(key) {
+            Some(value) => inherit(value, default),
+            None => {
+                table.insert(key.clone(), default.clone());
+            }
+        }
     }
 }
@@ -0,0 +1,151 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use reqwest::Url;
+use serde::de;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+/// Query parameters masked by [`redact_url`].
+const SECRET_PARAMS: [&str; 11] = [
+    "passkey",
+    "torrent_pass",
+    "authkey",
+    "token",
+    "apikey",
+    "jackett_apikey",
+    "api_key",
+    "key",
+    "secret",
+    "sign",
+    "auth",
+];
+
+/// A secret in the config, eg. a passkey in an rss link or an app secret.
+///
+/// Can be written as `env:NAME` to read the environment variable `NAME`, or
+/// `file:PATH` to read the file at `PATH`. `Debug` never shows the secret, urls
+/// are shown with the credentials masked by [`redact_url`].
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Resolve `env:` and `file:` references, other values are kept as is.
+    fn resolve(value: String) -> Result<Self, String> {
+        if let Some(name) = value.strip_prefix("env:") {
+            std::env::var(name)
+                .map(Self)
+                .map_err(|e| format!("read environment variable '{}' failed: {}", name, e))
+        } else if let Some(path) = value.strip_prefix("file:") {
+            std::fs::read_to_string(path)
+                .map(|content| Self(content.trim_end_matches(['\r', '\n']).to_string()))
+                .map_err(|e| format!("read secret file '{}' failed: {}", path, e))
+        } else {
+            Ok(Self(value))
+        }
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match Url::parse(&self.0) {
+            Ok(url) if url.has_host() => write!(f, "{:?}", redact_url(url.as_str())),
+            _ if self.0.is_empty() => write!(f, "\"\""),
+            _ => write!(f, "\"***\""),
+        }
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(d)?;
+        Self::resolve(value).map_err(de::Error::custom)
+    }
+}
+
+/// Mask the password and the secret query parameters of a url, eg. `passkey`,
+/// so that it can be logged. Invalid urls are masked entirely.
+pub fn redact_url(url: &str) -> String {
+    let Ok(mut url) = Url::parse(url) else {
+        return "***".to_string();
+    };
+    if url.password().is_some() {
+        let _ = url.set_password(Some("***"));
+    }
+    if url.query().is_some() {
+        let pairs = url
+            .query_pairs()
+            .map(|(key, value)| {
+                let secret = is_secret_param(&key);
+                (
+                    key.into_owned(),
+                    if secret {
+                        "***".into()
+                    } else {
+                        value.into_owned()
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+/// Whether the url has a password or a secret query parameter masked by
+/// [`redact_url`]. Invalid urls are treated as secrets.
+pub fn has_secrets(url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return true;
+    };
+    url.password().is_some() || url.query_pairs().any(|(key, _)| is_secret_param(&key))
+}
+
+fn is_secret_param(key: &str) -> bool {
+    SECRET_PARAMS.contains(&key.to_lowercase().as_str())
+}
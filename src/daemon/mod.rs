@@ -0,0 +1,207 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The pid file and the log file of the daemon mode.
+
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::bail;
+use anyhow::Context;
+use chrono::DateTime;
+use chrono::Local;
+
+use crate::DaemonConfig;
+use crate::LogRotation;
+use crate::Result;
+
+/// Whether the daemon is running, by its pid file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaemonStatus {
+    Running(i32),
+    /// The pid file is left by a daemon that is gone, eg. killed by SIGKILL.
+    Dead(i32),
+    NotRunning,
+}
+
+impl DaemonStatus {
+    /// The exit codes of `status` of LSB init scripts.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            DaemonStatus::Running(_) => 0,
+            DaemonStatus::Dead(_) => 1,
+            DaemonStatus::NotRunning => 3,
+        }
+    }
+}
+
+impl fmt::Display for DaemonStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DaemonStatus::Running(pid) => write!(f, "running (pid {pid})"),
+            DaemonStatus::Dead(pid) => {
+                write!(f, "not running, but the pid file exists (pid {pid})")
+            }
+            DaemonStatus::NotRunning => write!(f, "not running"),
+        }
+    }
+}
+
+/// The daemon holds an exclusive lock of its pid file until it exits, so the pid
+/// is never mistaken for another process that reuses it.
+pub fn status(pid_file: &str) -> Result<DaemonStatus> {
+    let file = match File::open(pid_file) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(DaemonStatus::NotRunning),
+        Err(e) => return Err(e).with_context(|| format!("Open pid file '{pid_file}' failed")),
+    };
+    let locked = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_SH | libc::LOCK_NB) } != 0;
+
+    let content = fs::read_to_string(pid_file)?;
+    let pid = match content.trim().parse::<i32>() {
+        Ok(pid) => pid,
+        // The pid is written after the file is locked.
+        Err(_) if locked => 0,
+        Err(_) => bail!("Invalid pid file '{}': {:?}", pid_file, content.trim()),
+    };
+    Ok(if locked {
+        DaemonStatus::Running(pid)
+    } else {
+        DaemonStatus::Dead(pid)
+    })
+}
+
+/// Send SIGTERM to the daemon and wait for it to exit. A pid file left by a dead
+/// daemon is removed.
+///
+/// Returns the pid if the daemon was running.
+pub fn stop(pid_file: &str, timeout: Duration) -> Result<Option<i32>> {
+    let pid = match status(pid_file)? {
+        DaemonStatus::Running(pid) => pid,
+        DaemonStatus::Dead(_) => {
+            fs::remove_file(pid_file)?;
+            return Ok(None);
+        }
+        DaemonStatus::NotRunning => return Ok(None),
+    };
+    if pid <= 0 {
+        bail!("The daemon is starting, try again later");
+    }
+    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+        bail!(
+            "Send SIGTERM to {} failed: {}",
+            pid,
+            io::Error::last_os_error()
+        );
+    }
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(100));
+        if !matches!(status(pid_file)?, DaemonStatus::Running(_)) {
+            return Ok(Some(pid));
+        }
+    }
+    bail!("The daemon (pid {}) did not stop in {:?}", pid, timeout)
+}
+
+/// The log file of the daemon, rotated by size or time.
+pub struct LogFile {
+    path: PathBuf,
+    max_size: u64,
+    rotation: LogRotation,
+    keep: usize,
+    /// When the current file is opened.
+    opened: DateTime<Local>,
+}
+
+impl LogFile {
+    /// Open the log file for appending.
+    pub fn open(config: &DaemonConfig) -> Result<(Self, File)> {
+        let log = Self {
+            path: PathBuf::from(&config.log_file),
+            max_size: config.log_max_size,
+            rotation: config.log_rotation,
+            keep: config.log_keep,
+            opened: Local::now(),
+        };
+        let file = log.append()?;
+        Ok((log, file))
+    }
+
+    pub fn should_rotate(&self, now: DateTime<Local>) -> bool {
+        let too_large = self.max_size > 0
+            && fs::metadata(&self.path).map_or(false, |m| m.len() >= self.max_size);
+        let period = |time: DateTime<Local>| match self.rotation {
+            LogRotation::Never => None,
+            LogRotation::Hourly => Some(time.format("%Y%m%d%H").to_string()),
+            LogRotation::Daily => Some(time.format("%Y%m%d").to_string()),
+        };
+        too_large || period(now) != period(self.opened)
+    }
+
+    /// Move `blooming.log` to `blooming.log.1`, the older files one step further,
+    /// and open a new file.
+    pub fn rotate(&mut self) -> Result<File> {
+        for i in (1..self.keep).rev() {
+            let from = self.rotated(i);
+            if from.exists() {
+                fs::rename(from, self.rotated(i + 1))?;
+            }
+        }
+        let result = if self.keep > 0 {
+            fs::rename(&self.path, self.rotated(1))
+        } else {
+            fs::remove_file(&self.path)
+        };
+        match result {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        self.opened = Local::now();
+        self.append()
+    }
+
+    fn append(&self) -> Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Open log file '{}' failed", self.path.display()))
+    }
+
+    fn rotated(&self, i: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{i}"));
+        path.into()
+    }
+}
+
+/// Redirect stdout and stderr to `file`, so that the logs and panics are written to it.
+pub fn redirect_stdio(file: &File) -> Result<()> {
+    for fd in [libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        if unsafe { libc::dup2(file.as_raw_fd(), fd) } < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+    }
+    Ok(())
+}
@@ -12,12 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod check;
+mod collapse;
 mod config;
+pub mod daemon;
+pub mod logger;
+pub mod metrics;
 pub mod notifier;
+pub mod poll;
+pub mod release;
+mod retry;
+mod schedule;
 pub mod source;
+pub mod state;
+pub mod supervisor;
+pub mod torrent;
 
+pub use collapse::Collapser;
+pub use collapse::HeldEpisode;
 pub use config::*;
 pub use notifier::*;
+pub use retry::error_kind;
+pub use retry::is_retryable;
+pub use retry::RetryPolicy;
+pub use schedule::AdaptiveInterval;
+pub use schedule::Schedule;
 pub use source::*;
 
 pub type Result<T> = anyhow::Result<T>;
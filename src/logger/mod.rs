@@ -0,0 +1,237 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The logger of blooming, in text or JSON, with levels by module.
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use chrono::Local;
+use chrono::SecondsFormat;
+use log::kv;
+use log::kv::VisitSource;
+use log::Level;
+use log::LevelFilter;
+use log::Log;
+use log::Metadata;
+use log::Record;
+
+use crate::LogConfig;
+use crate::LogFormat;
+
+/// The level of blooming if not configured.
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+/// The level of other crates if not configured.
+const DEFAULT_OTHERS_LEVEL: LevelFilter = LevelFilter::Warn;
+
+static LOGGER: Logger = Logger {
+    inner: RwLock::new(None),
+};
+
+struct Logger {
+    inner: RwLock<Option<(LogFormat, Filters)>>,
+}
+
+/// Install the logger, or apply the new config if it is installed.
+///
+/// `RUST_LOG` is applied on top of the config.
+pub fn init(config: &LogConfig) {
+    let env = std::env::var("RUST_LOG").ok();
+    let filters = Filters::new(config, env.as_deref());
+    log::set_max_level(filters.max());
+    *LOGGER.inner.write().unwrap() = Some((config.format, filters));
+    // Already installed if it fails.
+    let _ = log::set_logger(&LOGGER);
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match &*self.inner.read().unwrap() {
+            Some((_, filters)) => metadata.level() <= filters.level(metadata.target()),
+            None => false,
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        let line = match &*self.inner.read().unwrap() {
+            Some((format, filters)) if record.level() <= filters.level(record.target()) => {
+                match format {
+                    LogFormat::Text => format_text(record, stderr_is_terminal()),
+                    LogFormat::Json => format_json(record),
+                }
+            }
+            _ => return,
+        };
+        let _ = std::io::stderr().lock().write_all(line.as_bytes());
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+/// Levels by module path, the longest matching path wins.
+#[derive(Clone, Debug)]
+pub struct Filters {
+    default: LevelFilter,
+    /// Sorted by the length of the path, the longest first.
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Filters {
+    /// `env` is in the syntax of `RUST_LOG`. A single level, eg. `debug`, is the
+    /// level of blooming. Otherwise a single level is the level of other crates,
+    /// eg. `warn,blooming::source=debug`.
+    pub fn new(config: &LogConfig, env: Option<&str>) -> Self {
+        let mut filters = Self {
+            default: DEFAULT_OTHERS_LEVEL,
+            modules: vec![(
+                "blooming".to_string(),
+                config.level.unwrap_or(DEFAULT_LEVEL),
+            )],
+        };
+        for (module, level) in &config.modules {
+            filters.set(module, *level);
+        }
+
+        let env = env.unwrap_or_default();
+        for directive in env.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (module, level) = match directive.split_once('=') {
+                Some((module, level)) => (Some(module.trim()), level.trim()),
+                None if !env.contains('=') => (Some("blooming"), directive),
+                None => (None, directive),
+            };
+            let Ok(level) = LevelFilter::from_str(level) else {
+                eprintln!("Ignore invalid directive '{directive}' of RUST_LOG");
+                continue;
+            };
+            match module {
+                Some(module) => filters.set(module, level),
+                None => filters.default = level,
+            }
+        }
+        filters
+    }
+
+    fn set(&mut self, module: &str, level: LevelFilter) {
+        self.modules.retain(|(m, _)| m != module);
+        self.modules.push((module.to_string(), level));
+        self.modules.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()));
+    }
+
+    pub fn level(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .find(|(module, _)| {
+                target
+                    .strip_prefix(module.as_str())
+                    .map_or(false, |rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    pub fn max(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .chain([self.default])
+            .max()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Eg. ` 2023-05-01 12:00:00.000 - INFO  blooming > Notified 2 new items source=Nyaa items=2`.
+pub fn format_text(record: &Record, color: bool) -> String {
+    let time = Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
+    let level = format!("{:<5}", record.level());
+    let level = match (color, record.level()) {
+        (false, _) => level,
+        (true, Level::Error) => format!("\x1b[31m{level}\x1b[0m"),
+        (true, Level::Warn) => format!("\x1b[33m{level}\x1b[0m"),
+        (true, Level::Info) => format!("\x1b[32m{level}\x1b[0m"),
+        (true, Level::Debug) => format!("\x1b[34m{level}\x1b[0m"),
+        (true, Level::Trace) => format!("\x1b[35m{level}\x1b[0m"),
+    };
+    let mut line = format!(
+        " {} - {} {} > {}",
+        time,
+        level,
+        record.target(),
+        record.args()
+    );
+    for (key, value) in fields(record) {
+        match value {
+            serde_json::Value::String(s) if !s.is_empty() && !s.contains(char::is_whitespace) => {
+                let _ = write!(line, " {key}={s}");
+            }
+            value => {
+                let _ = write!(line, " {key}={value}");
+            }
+        }
+    }
+    line.push('\n');
+    line
+}
+
+/// One object per line, with the time, level, target, message and the fields of the record.
+pub fn format_json(record: &Record) -> String {
+    let mut object = serde_json::Map::new();
+    let time = Local::now().to_rfc3339_opts(SecondsFormat::Millis, false);
+    object.insert("time".to_string(), time.into());
+    object.insert("level".to_string(), record.level().as_str().into());
+    object.insert("target".to_string(), record.target().into());
+    object.insert("message".to_string(), record.args().to_string().into());
+    for (key, value) in fields(record) {
+        object.entry(key).or_insert(value);
+    }
+    let mut line = serde_json::Value::Object(object).to_string();
+    line.push('\n');
+    line
+}
+
+fn fields(record: &Record) -> Vec<(String, serde_json::Value)> {
+    struct Fields(Vec<(String, serde_json::Value)>);
+
+    impl<'kvs> VisitSource<'kvs> for Fields {
+        fn visit_pair(
+            &mut self,
+            key: kv::Key<'kvs>,
+            value: kv::Value<'kvs>,
+        ) -> Result<(), kv::Error> {
+            let value = if let Some(b) = value.to_bool() {
+                b.into()
+            } else if let Some(n) = value.to_u64() {
+                n.into()
+            } else if let Some(n) = value.to_i64() {
+                n.into()
+            } else if let Some(n) = value.to_f64().and_then(serde_json::Number::from_f64) {
+                n.into()
+            } else {
+                value.to_string().into()
+            };
+            self.0.push((key.to_string(), value));
+            Ok(())
+        }
+    }
+
+    let mut fields = Fields(vec![]);
+    let _ = record.key_values().visit(&mut fields);
+    fields.0
+}
+
+fn stderr_is_terminal() -> bool {
+    unsafe { libc::isatty(libc::STDERR_FILENO) == 1 }
+}
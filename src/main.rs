@@ -12,158 +12,296 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-#![feature(try_blocks)]
-
 use std::env::current_dir;
-use std::fs::File;
-use std::path::PathBuf;
-
-use backon::ConstantBuilder;
-use backon::Retryable;
-use blooming::notifier;
-use blooming::source::register;
-use blooming::source::SourceFactory;
-use blooming::source::SourcePtr;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use blooming::check::check_config;
+use blooming::check::check_live;
+use blooming::check::notify_test;
+use blooming::daemon;
+use blooming::daemon::DaemonStatus;
+use blooming::daemon::LogFile;
+use blooming::format_size;
+use blooming::logger;
+use blooming::metrics;
+use blooming::poll;
+use blooming::poll::Poller;
+use blooming::poll::SHUTDOWN_TIMEOUT;
+use blooming::state::format_episodes;
+use blooming::state::StateStore;
 use blooming::ClapConfig;
+use blooming::Command;
 use blooming::Config;
-use blooming::Notifier;
-use blooming::QQGuildNotifier;
-use blooming::QQNotifier;
+use blooming::LogConfig;
 use blooming::Result;
 use chrono::Local;
 use clap::Parser;
 use daemonize::Daemonize;
 use log::error;
 use log::info;
-use reqwest::Client;
+use tokio::net::TcpListener;
+use tokio::signal::unix::signal;
+use tokio::signal::unix::SignalKind;
 use tokio::task::JoinHandle;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-async fn main_impl(config: Config) -> Result<()> {
-    let mut factory = SourceFactory::default();
-    register(&mut factory, &config)?;
+/// How often to check if the config file is modified and the log file should be rotated.
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
 
-    let client = Client::new();
-    let mut handles = Vec::new();
+/// Run the sources, and reload the config on SIGHUP or when the file is modified.
+/// Stops gracefully on SIGTERM or SIGINT.
+async fn main_impl(
+    config: Config,
+    config_file: &str,
+    dry_run: bool,
+    mut log: Option<LogFile>,
+) -> Result<()> {
+    let metrics_server = match &config.metrics {
+        Some(metrics) => Some(serve_metrics(metrics.listen).await?),
+        None => None,
+    };
+    let mut poller = Poller::start(config, dry_run)?;
 
-    if let Some(qq) = config.qq.clone() {
-        let notifier = notifier::QQNotifier::new(client.clone(), qq);
-        handles.extend(activate_qq_notifier(&factory, notifier));
-    }
-    if let Some(qq_guild) = config.qq_guild.clone() {
-        let notifier = notifier::QQGuildNotifier::new(client, qq_guild);
-        handles.extend(activate_qq_guild_notifier(&factory, notifier));
+    let mut hangup = signal(SignalKind::hangup())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut user_defined = signal(SignalKind::user_defined1())?;
+    let mut watch = tokio::time::interval(WATCH_INTERVAL);
+    let mut modified = modified_time(config_file);
+    loop {
+        tokio::select! {
+            _ = terminate.recv() => {
+                info!("Received SIGTERM, stopping");
+                break;
+            }
+            _ = interrupt.recv() => {
+                info!("Received SIGINT, stopping");
+                break;
+            }
+            _ = user_defined.recv() => {
+                poller.log_health();
+                continue;
+            }
+            _ = hangup.recv() => info!("Received SIGHUP, reloading config"),
+            _ = watch.tick() => {
+                if let Some(log) = log.as_mut().filter(|log| log.should_rotate(Local::now())) {
+                    if let Err(e) = rotate_log(log) {
+                        error!("Rotate log file failed: {:#}", e);
+                    }
+                }
+                let time = modified_time(config_file);
+                if time == modified {
+                    continue;
+                }
+                modified = time;
+                info!("Config file is modified, reloading config");
+            }
+        }
+        if let Err(e) = reload(&mut poller, config_file).await {
+            error!(
+                "Reload config failed, keep running with the old config: {:#}",
+                e
+            );
+        }
     }
 
-    futures::future::join_all(handles).await;
+    poller.shutdown().await;
+    if let Some(server) = metrics_server {
+        server.abort();
+    }
+    info!("Stopped");
+    Ok(())
+}
 
+/// Load the config again and apply it to the running tasks.
+async fn reload(poller: &mut Poller, config_file: &str) -> Result<()> {
+    let config = Config::load(config_file)?;
+    poller.reload(config).await?;
     Ok(())
 }
 
-fn activate_qq_notifier(factory: &SourceFactory, notifier: QQNotifier) -> Vec<JoinHandle<()>> {
-    let sources = factory.sources();
-    sources
-        .iter()
-        .map(|source| {
-            let source = source.clone();
-            let n = notifier.clone();
-            tokio::spawn(async move {
-                run(source, n).await;
-            })
-        })
-        .collect::<Vec<_>>()
+async fn serve_metrics(addr: SocketAddr) -> Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Listen on {} for metrics failed", addr))?;
+    info!("Serving metrics at http://{}/metrics", addr);
+    Ok(tokio::spawn(async move {
+        if let Err(e) = metrics::serve(listener, metrics::REQUEST_TIMEOUT).await {
+            error!("Serve metrics failed: {}", e);
+        }
+    }))
 }
 
-fn activate_qq_guild_notifier(
-    factory: &SourceFactory,
-    notifier: QQGuildNotifier,
-) -> Vec<JoinHandle<()>> {
-    let sources = factory.sources();
-    sources
-        .iter()
-        .map(|source| {
-            let source = source.clone();
-            let n = notifier.clone();
-            tokio::spawn(async move {
-                run(source, n).await;
-            })
-        })
-        .collect::<Vec<_>>()
+fn rotate_log(log: &mut LogFile) -> Result<()> {
+    let file = log.rotate()?;
+    daemon::redirect_stdio(&file)?;
+    info!("Rotated log file");
+    Ok(())
 }
 
-async fn run<T: Notifier>(source: SourcePtr, mut notifier: T) {
-    if source.check_connection().await.is_err() {
-        error!("Check connection of '{}' failed", source.name());
-    } else {
-        info!("Check connection of '{}' successful", source.name());
-    }
+fn modified_time(file: &str) -> Option<SystemTime> {
+    std::fs::metadata(file).and_then(|m| m.modified()).ok()
+}
 
-    let mut last_update = Local::now();
-    let interval = source.interval();
-    let retry_config = ConstantBuilder::default();
-    let each_notify = notifier.num_items_each_notify();
+fn show_episodes(config: &Config, series: Option<String>) -> Result<()> {
+    let path = config
+        .state_file
+        .as_deref()
+        .ok_or_else(|| anyhow!("`state_file` is not configured"))?;
+    let state = StateStore::open(Some(path))?;
+    let expected = config
+        .episodes
+        .as_ref()
+        .map(|e| e.series.as_slice())
+        .unwrap_or_default();
 
-    loop {
-        tokio::time::sleep(interval).await;
-
-        let result: Result<()> = try {
-            let fetch = || async { source.pull_items().await };
-            let items = fetch.retry(&retry_config).await?;
-            let new_items = items
-                .into_iter()
-                .filter(|item| item.pub_date > last_update)
-                .collect::<Vec<_>>();
-
-            if !new_items.is_empty() {
-                // update the time marker
-                last_update = new_items.iter().fold(new_items[0].pub_date, |acc, item| {
-                    let pub_time = item.pub_date;
-                    if pub_time > acc { pub_time } else { acc }
-                });
-
-                // notify
-                if each_notify == 0 {
-                    notifier.notify(&source.name(), new_items.clone()).await?;
-                } else {
-                    for chunk in new_items.chunks(each_notify) {
-                        notifier.notify(&source.name(), chunk.to_vec()).await?;
-                    }
-                }
-            }
+    state.read(|state| {
+        let records = match &series {
+            Some(name) => state.ledger.search(name),
+            None => state.ledger.series.iter().collect(),
         };
+        for record in records {
+            let mut line = format!(
+                "{}: {}",
+                record.display_name(),
+                format_episodes(record.episodes.keys().copied())
+            );
+            let missing = record.missing();
+            if !missing.is_empty() {
+                line.push_str(&format!(", missing {}", format_episodes(missing)));
+            }
+            if let Some(expected) = record.expected(expected) {
+                line.push_str(&format!(", expected {expected}"));
+            }
+            println!("{line}");
+        }
+    });
+    Ok(())
+}
 
-        if let Err(e) = result {
-            error!("{}", e);
+/// Pull the source once and print the items, the latest first.
+async fn fetch(config: &Config, name: &str, json: bool) -> Result<()> {
+    let items = poll::fetch(config, name).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&items)?);
+        return Ok(());
+    }
+
+    println!("{:<16}  {:>10}  TITLE", "DATE", "SIZE");
+    for item in &items {
+        let size = item.total_size().map(format_size).unwrap_or_default();
+        let mut title = item.title.clone();
+        if let Some(label) = &item.label {
+            title.push_str(&format!(" ({label})"));
         }
+        println!(
+            "{:<16}  {:>10}  {}",
+            item.pub_date.format("%Y-%m-%d %H:%M"),
+            size,
+            title
+        );
     }
+    Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    sensible_env_logger::init_timed_local!();
+/// Print the report of checking the config, returns the exit code.
+async fn check(config_file: &str, live: bool) -> i32 {
+    let config = match Config::load(config_file) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("FAIL  config: {e}");
+            return 2;
+        }
+    };
+
+    let mut report = check_config(&config);
+    if live {
+        report.extend(check_live(&config).await);
+    }
+    print!("ok    config: {config_file}\n{report}");
+    report.exit_code()
+}
+
+fn main() -> Result<()> {
+    logger::init(&LogConfig::default());
 
     let args = ClapConfig::parse();
+    // Built after daemonizing, the threads of a runtime do not survive fork.
+    let runtime = tokio::runtime::Runtime::new;
+
+    if let Some(Command::Check { live }) = args.command {
+        let code = runtime()?.block_on(check(&args.config_file, live));
+        std::process::exit(code);
+    }
 
     let config = Config::load(&args.config_file)?;
+    logger::init(&config.log);
+    match &args.command {
+        Some(Command::Episodes { series }) => return show_episodes(&config, series.clone()),
+        Some(Command::Fetch { source, json }) => {
+            return runtime()?.block_on(fetch(&config, source, *json));
+        }
+        Some(Command::Once) => return runtime()?.block_on(poll::once(config, args.dry_run)),
+        Some(Command::NotifyTest { notifier }) => {
+            let report =
+                runtime()?.block_on(notify_test(&config, notifier.as_deref(), args.dry_run))?;
+            print!("{report}");
+            std::process::exit(report.exit_code());
+        }
+        Some(Command::Stop) => return stop(&config),
+        Some(Command::Status) => {
+            let status = daemon::status(&config.daemon.pid_file)?;
+            println!("blooming is {status}");
+            std::process::exit(status.exit_code());
+        }
+        Some(Command::Restart) => stop(&config)?,
+        Some(Command::Check { .. }) | None => {}
+    }
+    let daemonize = args.daemonize || matches!(args.command, Some(Command::Restart));
+
     info!("Welcome to use blooming (version: {})", VERSION);
     info!("Starting blooming with config: {:?}", config);
 
-    if args.daemonize {
-        let current_dir = current_dir()?;
-        let log_file = PathBuf::from(format!("{}/blooming.log", current_dir.display()));
-        let pid_file = PathBuf::from(format!("{}/blooming.pid", current_dir.display()));
-
-        let stdout = File::create(log_file.clone())?;
-        let stderr = File::create(log_file)?;
-        let daemon = Daemonize::new()
-            .pid_file(pid_file)
-            .working_directory(current_dir)
-            .stdout(stdout)
-            .stderr(stderr);
+    let log = if daemonize {
+        Some(start_daemon(&config)?)
+    } else {
+        None
+    };
+    let pid_file = config.daemon.pid_file.clone();
+    let result = runtime()?.block_on(main_impl(config, &args.config_file, args.dry_run, log));
+    if daemonize {
+        let _ = std::fs::remove_file(pid_file);
+    }
+    result
+}
 
-        daemon.start()?;
+/// Fork into the background with stdout and stderr redirected to the log file.
+fn start_daemon(config: &Config) -> Result<LogFile> {
+    let pid_file = &config.daemon.pid_file;
+    if let DaemonStatus::Running(pid) = daemon::status(pid_file)? {
+        bail!("blooming is already running (pid {})", pid);
     }
 
-    main_impl(config).await
+    let (log, file) = LogFile::open(&config.daemon)?;
+    Daemonize::new()
+        .pid_file(pid_file)
+        .working_directory(current_dir()?)
+        .stdout(file.try_clone()?)
+        .stderr(file)
+        .start()?;
+    Ok(log)
+}
+
+fn stop(config: &Config) -> Result<()> {
+    match daemon::stop(&config.daemon.pid_file, SHUTDOWN_TIMEOUT + WATCH_INTERVAL)? {
+        Some(pid) => println!("Stopped blooming (pid {pid})"),
+        None => println!("blooming is not running"),
+    }
+    Ok(())
 }
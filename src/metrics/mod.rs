@@ -0,0 +1,271 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use log::debug;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+
+use crate::Result;
+
+/// How long a client of the metrics endpoint has to send the request and read the response.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Buckets of the histograms, in seconds.
+pub const DEFAULT_BUCKETS: [f64; 11] =
+    [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+/// Labels of a sample, sorted by name.
+type Labels = Vec<(String, String)>;
+
+/// Metrics of blooming, rendered in the Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    families: Mutex<BTreeMap<String, Family>>,
+}
+
+struct Family {
+    help: String,
+    kind: &'static str,
+    samples: BTreeMap<Labels, Sample>,
+}
+
+enum Sample {
+    Value(f64),
+    Histogram(Histogram),
+}
+
+struct Histogram {
+    /// Cumulative counts of [`DEFAULT_BUCKETS`].
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+/// The metrics of the process.
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::default)
+}
+
+impl Metrics {
+    /// Set the value of a gauge.
+    pub fn set_gauge(&self, name: &str, help: &str, labels: &[(&str, &str)], value: f64) {
+        let mut families = self.families.lock().unwrap();
+        let family = family(&mut families, name, help, "gauge");
+        family
+            .samples
+            .insert(to_labels(labels), Sample::Value(value));
+    }
+
+    /// Set a gauge to the current unix time, eg. of the last success.
+    pub fn set_timestamp(&self, name: &str, help: &str, labels: &[(&str, &str)]) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.set_gauge(name, help, labels, now.as_secs_f64());
+    }
+
+    /// Increase the value of a counter.
+    pub fn inc_counter(&self, name: &str, help: &str, labels: &[(&str, &str)], value: f64) {
+        let mut families = self.families.lock().unwrap();
+        let family = family(&mut families, name, help, "counter");
+        let sample = family
+            .samples
+            .entry(to_labels(labels))
+            .or_insert(Sample::Value(0.0));
+        if let Sample::Value(v) = sample {
+            *v += value;
+        }
+    }
+
+    /// Record a value of a histogram with [`DEFAULT_BUCKETS`].
+    pub fn observe(&self, name: &str, help: &str, labels: &[(&str, &str)], value: f64) {
+        let mut families = self.families.lock().unwrap();
+        let family = family(&mut families, name, help, "histogram");
+        let sample = family.samples.entry(to_labels(labels)).or_insert_with(|| {
+            Sample::Histogram(Histogram {
+                buckets: vec![0; DEFAULT_BUCKETS.len()],
+                sum: 0.0,
+                count: 0,
+            })
+        });
+        if let Sample::Histogram(h) = sample {
+            for (bucket, le) in h.buckets.iter_mut().zip(DEFAULT_BUCKETS) {
+                if value <= le {
+                    *bucket += 1;
+                }
+            }
+            h.sum += value;
+            h.count += 1;
+        }
+    }
+
+    /// Remove a sample, eg. of a task that is stopped.
+    pub fn remove(&self, name: &str, labels: &[(&str, &str)]) {
+        let mut families = self.families.lock().unwrap();
+        if let Some(family) = families.get_mut(name) {
+            family.samples.remove(&to_labels(labels));
+        }
+    }
+
+    /// The current value of a metric, or the number of values of a histogram.
+    pub fn get(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        let families = self.families.lock().unwrap();
+        match families.get(name)?.samples.get(&to_labels(labels))? {
+            Sample::Value(v) => Some(*v),
+            Sample::Histogram(h) => Some(h.count as f64),
+        }
+    }
+
+    /// Render all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let families = self.families.lock().unwrap();
+        let mut out = String::new();
+        for (name, family) in families.iter() {
+            let _ = writeln!(out, "# HELP {} {}", name, family.help);
+            let _ = writeln!(out, "# TYPE {} {}", name, family.kind);
+            for (labels, sample) in family.samples.iter() {
+                match sample {
+                    Sample::Value(value) => {
+                        let _ = writeln!(out, "{}{} {}", name, render_labels(labels), value);
+                    }
+                    Sample::Histogram(h) => render_histogram(&mut out, name, labels, h),
+                }
+            }
+        }
+        out
+    }
+}
+
+fn family<'a>(
+    families: &'a mut BTreeMap<String, Family>,
+    name: &str,
+    help: &str,
+    kind: &'static str,
+) -> &'a mut Family {
+    families.entry(name.to_string()).or_insert_with(|| Family {
+        help: help.to_string(),
+        kind,
+        samples: BTreeMap::new(),
+    })
+}
+
+fn render_histogram(out: &mut String, name: &str, labels: &Labels, h: &Histogram) {
+    let with_le = |le: String| {
+        let mut labels = labels.clone();
+        labels.push(("le".to_string(), le));
+        render_labels(&labels)
+    };
+    for (count, le) in h.buckets.iter().zip(DEFAULT_BUCKETS) {
+        let _ = writeln!(out, "{}_bucket{} {}", name, with_le(le.to_string()), count);
+    }
+    let _ = writeln!(
+        out,
+        "{}_bucket{} {}",
+        name,
+        with_le("+Inf".to_string()),
+        h.count
+    );
+    let _ = writeln!(out, "{}_sum{} {}", name, render_labels(labels), h.sum);
+    let _ = writeln!(out, "{}_count{} {}", name, render_labels(labels), h.count);
+}
+
+/// Serve [`metrics`] at `GET /metrics` until the task is dropped.
+///
+/// Connections that do not finish the request and the response in `timeout`, eg.
+/// never send a full request line, are closed.
+pub async fn serve(listener: TcpListener, timeout: Duration) -> Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        tokio::spawn(async move {
+            match tokio::time::timeout(timeout, handle(stream)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => debug!("Serve metrics to {} failed: {}", peer, e),
+                Err(_) => debug!("Serve metrics to {} timed out", peer),
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream) -> Result<()> {
+    // Only the request line matters, the headers and body are ignored.
+    let mut buf = vec![0; 4096];
+    let mut len = 0;
+    while !buf[..len].windows(2).any(|w| w == b"\r\n") && len < buf.len() {
+        let n = stream.read(&mut buf[len..]).await?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+    }
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let mut parts = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let method = parts.next();
+    // scrapers may add query parameters
+    let path = parts.next().and_then(|target| target.split('?').next());
+    let (status, body) = match (method, path) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics().render()),
+        (Some("GET"), _) => ("404 Not Found", "Not Found\n".to_string()),
+        _ => ("405 Method Not Allowed", "Method Not Allowed\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+fn to_labels(labels: &[(&str, &str)]) -> Labels {
+    let mut labels = labels
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<Vec<_>>();
+    labels.sort();
+    labels
+}
+
+fn render_labels(labels: &Labels) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels = labels
+        .iter()
+        .map(|(k, v)| {
+            let v = v
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{k}=\"{v}\"")
+        })
+        .collect::<Vec<_>>();
+    format!("{{{}}}", labels.join(","))
+}
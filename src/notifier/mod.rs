@@ -18,13 +18,38 @@ mod qq_guild;
 pub use qq::QQNotifier;
 pub use qq_guild::QQGuildNotifier;
 
+use crate::metrics::metrics;
 use crate::source::Item;
 use crate::Result;
 
 #[async_trait::async_trait]
 pub trait Notifier: Sync + Send + Clone {
+    /// The name of the notifier. Eg. QQ.
+    fn name(&self) -> String;
+
     async fn notify(&mut self, source: &str, items: Vec<Item>) -> Result<()>;
 
+    /// Notify a plain text message, eg. alerts of missing episodes.
+    async fn notify_text(&mut self, source: &str, text: &str) -> Result<()>;
+
+    /// The message that `notify` would send, for `--dry-run`.
+    fn preview(&self, source: &str, items: &[Item]) -> String;
+
+    /// The message that `notify_text` would send, for `--dry-run`.
+    fn preview_text(&self, source: &str, text: &str) -> String {
+        format!("{source}:\n{text}")
+    }
+
+    /// Verify the API and credentials without sending any message.
+    ///
+    /// Returns what is verified, eg. the account of the bot.
+    async fn check(&self) -> Result<String>;
+
+    /// Send `items` to each target separately without retrying, for `notify-test`.
+    ///
+    /// Returns the outcome of each target, eg. `group 123`.
+    async fn notify_test(&mut self, source: &str, items: Vec<Item>) -> Vec<(String, Result<()>)>;
+
     /// The number of items to be notified each time.
     ///
     /// If it is 0, all items will be notified at once.
@@ -32,3 +57,82 @@ pub trait Notifier: Sync + Send + Clone {
         0
     }
 }
+
+/// Prints the messages of the inner notifier instead of sending them, for `--dry-run`.
+#[derive(Clone)]
+pub struct DryRunNotifier<T> {
+    inner: T,
+}
+
+impl<T: Notifier> DryRunNotifier<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    fn print(&self, message: &str) {
+        println!("[dry-run] {} would send:\n{}\n", self.inner.name(), message);
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Notifier> Notifier for DryRunNotifier<T> {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    async fn notify(&mut self, source: &str, items: Vec<Item>) -> Result<()> {
+        self.print(&self.inner.preview(source, &items));
+        Ok(())
+    }
+
+    async fn notify_text(&mut self, source: &str, text: &str) -> Result<()> {
+        self.print(&self.inner.preview_text(source, text));
+        Ok(())
+    }
+
+    fn preview(&self, source: &str, items: &[Item]) -> String {
+        self.inner.preview(source, items)
+    }
+
+    fn preview_text(&self, source: &str, text: &str) -> String {
+        self.inner.preview_text(source, text)
+    }
+
+    async fn check(&self) -> Result<String> {
+        self.inner.check().await
+    }
+
+    async fn notify_test(&mut self, source: &str, items: Vec<Item>) -> Vec<(String, Result<()>)> {
+        self.print(&self.inner.preview(source, &items));
+        vec![("dry-run".to_string(), Ok(()))]
+    }
+
+    fn num_items_each_notify(&self) -> usize {
+        self.inner.num_items_each_notify()
+    }
+}
+
+/// Count a message sent to a target of a notifier, eg. `group 123`.
+fn record_notification(notifier: &str, target: &str, result: &Result<()>) {
+    let result = if result.is_ok() { "sent" } else { "failed" };
+    metrics().inc_counter(
+        "blooming_notifications_total",
+        "Number of messages sent to each target, after the retries.",
+        &[
+            ("notifier", notifier),
+            ("target", target),
+            ("result", result),
+        ],
+        1.0,
+    );
+}
+
+/// Count a retry of sending a message to a target of a notifier.
+fn record_retry(notifier: &str, target: &str) {
+    metrics().inc_counter(
+        "blooming_notification_retries_total",
+        "Number of retries of sending messages to each target.",
+        &[("notifier", notifier), ("target", target)],
+        1.0,
+    );
+}
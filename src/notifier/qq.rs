@@ -14,20 +14,28 @@
 
 use std::time::Duration;
 
+use anyhow::bail;
+use backon::Retryable;
 use log::error;
 use reqwest::Client;
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::record_notification;
+use super::record_retry;
+use crate::error_kind;
+use crate::is_retryable;
 use crate::source::Item;
 use crate::Notifier;
 use crate::QQBotConfig;
 use crate::Result;
+use crate::RetryPolicy;
 
 #[derive(Clone)]
 pub struct QQNotifier {
     client: Client,
     conf: QQBotConfig,
+    retry: RetryPolicy,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -42,6 +50,46 @@ struct GroupMsg {
     messages: Vec<Message>,
 }
 
+/// A message to a single user or group.
+trait Target {
+    /// Eg. `dm 123`, `group 456`.
+    fn target(&self) -> String;
+}
+
+impl Target for PrivateMsg {
+    fn target(&self) -> String {
+        format!("dm {}", self.user_id)
+    }
+}
+
+impl Target for GroupMsg {
+    fn target(&self) -> String {
+        format!("group {}", self.group_id)
+    }
+}
+
+/// The response of go-cqhttp actions, errors are reported by `retcode`.
+#[derive(Serialize, Deserialize)]
+struct ActionResp {
+    retcode: i32,
+    #[serde(default)]
+    msg: String,
+    #[serde(default)]
+    wording: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LoginInfoResp {
+    retcode: i32,
+    data: Option<LoginInfo>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LoginInfo {
+    user_id: u64,
+    nickname: String,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 struct Message {
     #[serde(rename = "type")]
@@ -60,71 +108,134 @@ struct Data {
 
 #[async_trait::async_trait]
 impl Notifier for QQNotifier {
+    fn name(&self) -> String {
+        "QQ".to_string()
+    }
+
     async fn notify(&mut self, source: &str, items: Vec<Item>) -> Result<()> {
+        let mut msgs = Vec::with_capacity(items.len() * 2);
+        for item in items.iter() {
+            msgs.extend(self.messages(source, item));
+        }
+        self.send(msgs).await;
+        Ok(())
+    }
+
+    async fn notify_text(&mut self, source: &str, text: &str) -> Result<()> {
+        let msgs = vec![self.node(format!("{source}:\n{text}"))];
+        self.send(msgs).await;
+        Ok(())
+    }
+
+    fn preview(&self, source: &str, items: &[Item]) -> String {
+        items
+            .iter()
+            .flat_map(|item| self.messages(source, item))
+            .map(|msg| msg.data.content)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    async fn check(&self) -> Result<String> {
+        let url = format!("{}/get_login_info", self.conf.api);
+        let resp = self
+            .client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<LoginInfoResp>()
+            .await?;
+        let info = match resp.data {
+            Some(info) if resp.retcode == 0 => info,
+            _ => bail!("get_login_info failed with retcode {}", resp.retcode),
+        };
+        let uin = info.user_id.to_string();
+        if !self.conf.uin.is_empty() && self.conf.uin != uin {
+            bail!("Logged in as {}, but `uin` is {}", uin, self.conf.uin);
+        }
+        Ok(format!("logged in as {} ({})", info.nickname, uin))
+    }
+
+    async fn notify_test(&mut self, source: &str, items: Vec<Item>) -> Vec<(String, Result<()>)> {
+        let msgs = items
+            .iter()
+            .flat_map(|item| self.messages(source, item))
+            .collect::<Vec<_>>();
+        let mut outcomes = Vec::with_capacity(self.conf.dms.len() + self.conf.groups.len());
+
+        let url = format!("{}/send_private_forward_msg", self.conf.api);
+        for msg in self.private_messages(msgs.clone()) {
+            let result = Self::post(&self.client, &url, &msg).await;
+            outcomes.push((msg.target(), result));
+        }
+        let url = format!("{}/send_group_forward_msg", self.conf.api);
+        for msg in self.group_messages(msgs) {
+            let result = Self::post(&self.client, &url, &msg).await;
+            outcomes.push((msg.target(), result));
+        }
+        outcomes
+    }
+}
+
+impl QQNotifier {
+    pub fn new(client: Client, conf: QQBotConfig) -> Self {
+        let retry = RetryPolicy::new(&conf.retry);
+        Self {
+            client,
+            conf,
+            retry,
+        }
+    }
+
+    async fn send(&self, msgs: Vec<Message>) {
         let delay = self.conf.delay;
+        let retry = &self.retry;
         let pm_handle = {
             let client = self.client.clone();
-            let pm_items = items.clone();
-            let source = source.to_string();
-            let mut msgs = Vec::with_capacity(pm_items.len() * 2);
-            for item in pm_items.iter() {
-                msgs.extend(self.messages(&source, item));
-            }
-            let msgs = self.private_messages(msgs);
+            let msgs = self.private_messages(msgs.clone());
             let url = format!("{}/send_private_forward_msg", self.conf.api);
-            async move {
-                if let Err(e) = Self::send_messages(client, &url, msgs, delay).await {
-                    error!("Send private msg failed: {}", e);
-                }
-            }
+            async move { Self::send_messages(client, &url, msgs, delay, retry).await }
         };
 
         let dm_handle = {
             let client = self.client.clone();
-            let gp_items = items;
-            let source = source.to_string();
-            let mut msgs = Vec::with_capacity(gp_items.len() * 2);
-            for item in gp_items.iter() {
-                msgs.extend(self.messages(&source, item));
-            }
             let msgs = self.group_messages(msgs);
             let url = format!("{}/send_group_forward_msg", self.conf.api);
-            async move {
-                if let Err(e) = Self::send_messages(client, &url, msgs, delay).await {
-                    error!("Send group msg failed: {}", e);
-                }
-            }
+            async move { Self::send_messages(client, &url, msgs, delay, retry).await }
         };
 
         tokio::join!(pm_handle, dm_handle);
-
-        Ok(())
-    }
-}
-
-impl QQNotifier {
-    pub fn new(client: Client, conf: QQBotConfig) -> Self {
-        Self { client, conf }
     }
 
-    fn messages(&self, source: &str, item: &Item) -> Vec<Message> {
-        let mut messages = vec![Message {
+    fn node(&self, content: String) -> Message {
+        Message {
             msg_type: "node".to_string(),
             data: Data {
                 sender_name: self.conf.name.clone(),
                 sender_uin: self.conf.uin.clone(),
-                content: format!("{}:\n{} ({})", source, item.title, item.pub_date),
+                content,
             },
-        }];
-        if self.conf.with_torrent {
-            messages.push(Message {
-                msg_type: "node".to_string(),
-                data: Data {
-                    sender_name: self.conf.name.clone(),
-                    sender_uin: self.conf.uin.clone(),
-                    content: item.url.clone(),
-                },
-            });
+        }
+    }
+
+    fn messages(&self, source: &str, item: &Item) -> Vec<Message> {
+        let mut content = format!("{}:\n{} ({})", source, item.title, item.pub_date);
+        if let Some(label) = &item.label {
+            content.push_str(&format!("\nSubscription: {label}"));
+        }
+        if let Some(alternatives) = item.alternatives_brief() {
+            content.push_str(&format!("\nAlternatives: {alternatives}"));
+        }
+        let mut messages = vec![self.node(content)];
+        // the url is left empty if it would leak a secret
+        if self.conf.with_torrent && !item.url.is_empty() {
+            messages.push(self.node(item.url.clone()));
+        }
+        if self.conf.with_magnet {
+            if let Some(magnet) = item.magnet() {
+                messages.push(self.node(magnet));
+            }
         }
         messages
     }
@@ -151,15 +262,50 @@ impl QQNotifier {
         msgs
     }
 
-    async fn send_messages<T: Serialize>(
+    /// Send the messages one by one. A failed target, eg. a group the bot has left,
+    /// is logged and does not stop the others.
+    async fn send_messages<T: Serialize + Target>(
         client: Client,
         url: &str,
         msgs: Vec<T>,
-        delay: u64,
-    ) -> Result<()> {
+        delay: Duration,
+        retry: &RetryPolicy,
+    ) {
         for msg in msgs.iter() {
-            client.post(url).json(msg).send().await?;
-            tokio::time::sleep(Duration::from_micros(delay)).await;
+            let target = msg.target();
+            let send = || Self::post(&client, url, msg);
+            let result = send
+                .retry(retry)
+                .when(is_retryable)
+                .notify(|_, _| record_retry("QQ", &target))
+                .await;
+            record_notification("QQ", &target, &result);
+            if let Err(e) = result {
+                error!(
+                    notifier = "QQ", target:% = target, kind = error_kind(&e);
+                    "Send msg to {} failed: {}", target, e
+                );
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn post<T: Serialize>(client: &Client, url: &str, msg: &T) -> Result<()> {
+        let resp = client
+            .post(url)
+            .json(msg)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<ActionResp>()
+            .await?;
+        if resp.retcode != 0 {
+            let reason = if resp.wording.is_empty() {
+                resp.msg
+            } else {
+                resp.wording
+            };
+            bail!("retcode {}: {}", resp.retcode, reason);
         }
         Ok(())
     }
@@ -12,15 +12,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::fmt;
+
+use backon::BackoffBuilder;
+use log::warn;
 use reqwest::Client;
 use reqwest::Response;
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::record_notification;
+use super::record_retry;
+use crate::error_kind;
+use crate::is_retryable;
+use crate::metrics::metrics;
 use crate::source::Item;
 use crate::Notifier;
 use crate::QQGuildBotConfig;
 use crate::Result;
+use crate::RetryPolicy;
 
 const API_GET_ACCESS_TOKEN: &str = "https://bots.qq.com/app/getAppAccessToken";
 const API_BOT: &str = "https://api.sgroup.qq.com";
@@ -34,7 +44,9 @@ pub struct QQGuildNotifier {
     conf: QQGuildBotConfig,
 
     api: String,
+    token_api: String,
     access_token: String,
+    retry: RetryPolicy,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -47,8 +59,14 @@ struct GetAppAccessTokenReq {
 
 #[derive(Serialize, Deserialize)]
 struct GetAppAccessTokenResp {
-    access_token: String,
-    expires_in: String,
+    access_token: Option<String>,
+    expires_in: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Channel {
+    name: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -60,37 +78,64 @@ struct Message {
 struct ErrorMessage {
     message: String,
     code: i32,
+    #[serde(default)]
     err_code: i32,
+    #[serde(default)]
     trace_id: String,
 }
 
+impl fmt::Display for ErrorMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (code {}, err_code {}, trace_id {})",
+            self.message, self.code, self.err_code, self.trace_id
+        )
+    }
+}
+
 #[async_trait::async_trait]
 impl Notifier for QQGuildNotifier {
-    async fn notify(&mut self, source: &str, items: Vec<Item>) -> Result<()> {
-        if self.access_token.is_empty() {
-            self.access_token = self.get_access_token().await?;
-        }
+    fn name(&self) -> String {
+        "QQ Guild".to_string()
+    }
 
+    async fn notify(&mut self, source: &str, items: Vec<Item>) -> Result<()> {
         let msg = self.message(source, items);
+        self.send_with_retry(&msg).await
+    }
 
-        let resp = self.send_message(&msg).await?;
-        let status_code = resp.status().as_u16();
+    async fn notify_text(&mut self, source: &str, text: &str) -> Result<()> {
+        let msg = Message {
+            content: format!("{source}:\n{text}"),
+        };
+        self.send_with_retry(&msg).await
+    }
 
-        if status_code == 401 {
-            // Get access token and retry.
-            self.access_token = self.get_access_token().await?;
-            self.send_message(&msg).await?;
-        } else if status_code == 500 {
-            let err_msg = resp.json::<ErrorMessage>().await?;
-            if err_msg.code == CODE_TOKEN_EXPIRED {
-                // Get access token and retry.
-                self.access_token = self.get_access_token().await?;
-                self.send_message(&msg).await?;
-            } else {
-                return Err(anyhow::anyhow!(err_msg.message));
-            }
-        }
-        Ok(())
+    fn preview(&self, source: &str, items: &[Item]) -> String {
+        self.message(source, items.to_vec()).content
+    }
+
+    async fn check(&self) -> Result<String> {
+        let access_token = self.get_access_token().await?;
+        let url = self.api.trim_end_matches("/messages");
+        let channel = self
+            .client
+            .get(url)
+            .header("Authorization", format!("QQBot {}", access_token))
+            .header("X-Union-Appid", &self.conf.app_id)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Channel>()
+            .await?;
+        Ok(format!("channel '{}'", channel.name))
+    }
+
+    async fn notify_test(&mut self, source: &str, items: Vec<Item>) -> Vec<(String, Result<()>)> {
+        let msg = self.message(source, items);
+        let result = self.send(&msg).await;
+        vec![(format!("channel {}", self.conf.channel_id), result)]
     }
 
     fn num_items_each_notify(&self) -> usize {
@@ -106,37 +151,148 @@ impl QQGuildNotifier {
             format!("{}/channels/{}/messages", API_BOT, conf.channel_id)
         };
 
+        let retry = RetryPolicy::new(&conf.retry);
         Self {
             client,
             conf,
             api,
+            token_api: API_GET_ACCESS_TOKEN.to_string(),
             access_token: "".to_string(),
+            retry,
         }
     }
 
+    /// Use other endpoints than the QQ API, eg. a stub server in tests.
+    ///
+    /// `api` replaces `https://api.sgroup.qq.com`, and `token_api` is the url to get
+    /// the access token.
+    pub fn with_endpoints(mut self, api: &str, token_api: &str) -> Self {
+        self.api = format!("{}/channels/{}/messages", api, self.conf.channel_id);
+        self.token_api = token_api.to_string();
+        self
+    }
+
     async fn get_access_token(&self) -> Result<String> {
         let body = GetAppAccessTokenReq {
             app_id: self.conf.app_id.clone(),
-            app_secret: self.conf.app_secret.clone(),
+            app_secret: self.conf.app_secret.expose().to_string(),
         };
-        let resp = self
-            .client
-            .post(API_GET_ACCESS_TOKEN)
-            .json(&body)
-            .send()
-            .await?;
+        let resp = self.client.post(&self.token_api).json(&body).send().await?;
         let resp = resp.json::<GetAppAccessTokenResp>().await?;
-        Ok(resp.access_token)
+        resp.access_token.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Get access token failed: {}",
+                resp.message.unwrap_or_default()
+            )
+        })
+    }
+
+    /// Get a new access token. `reason` is recorded in the metrics, eg. `expired`.
+    async fn refresh_access_token(&mut self, reason: &str) -> Result<()> {
+        let result = self.get_access_token().await;
+        metrics().inc_counter(
+            "blooming_token_refreshes_total",
+            "Number of access token refreshes of the QQ Guild bot.",
+            &[
+                ("notifier", "QQ Guild"),
+                ("reason", reason),
+                ("result", if result.is_ok() { "success" } else { "failure" }),
+            ],
+            1.0,
+        );
+        self.access_token = result?;
+        Ok(())
     }
 
     fn message(&self, source: &str, items: Vec<Item>) -> Message {
         let mut msg = format!("{source}:\n");
         for item in items {
             msg.push_str(&format!("{} ({})\n", item.title, item.pub_date));
+            if let Some(label) = &item.label {
+                msg.push_str(&format!("  Subscription: {label}\n"));
+            }
+            if let Some(alternatives) = item.alternatives_brief() {
+                msg.push_str(&format!("  Alternatives: {alternatives}\n"));
+            }
         }
         Message { content: msg }
     }
 
+    async fn send_with_retry(&mut self, msg: &Message) -> Result<()> {
+        let target = format!("channel {}", self.conf.channel_id);
+        let result = self.send_with_backoff(msg, &target).await;
+        record_notification("QQ Guild", &target, &result);
+        result
+    }
+
+    async fn send_with_backoff(&mut self, msg: &Message, target: &str) -> Result<()> {
+        let mut backoff = self.retry.build();
+        loop {
+            match self.send(msg).await {
+                Err(e) if is_retryable(&e) => match backoff.next() {
+                    Some(delay) => {
+                        record_retry("QQ Guild", target);
+                        warn!(
+                            notifier = "QQ Guild",
+                            target:% = self.conf.channel_id,
+                            kind = error_kind(&e);
+                            "Send message failed, retry after {:?}: {}", delay, e
+                        );
+                        tokio::time::sleep(delay).await;
+                    }
+                    None => return Err(e),
+                },
+                result => return result,
+            }
+        }
+    }
+
+    async fn send(&mut self, msg: &Message) -> Result<()> {
+        if self.access_token.is_empty() {
+            self.refresh_access_token("missing").await?;
+        }
+
+        let resp = self.send_message(msg).await?;
+        let status_code = resp.status().as_u16();
+
+        if status_code == 401 {
+            // Get access token and retry.
+            self.refresh_access_token("unauthorized").await?;
+            let resp = self.send_message(msg).await?;
+            Self::check_response(resp).await
+        } else if status_code == 500 {
+            // Keep the status error as the source, so that other errors are retried.
+            let Err(status_error) = resp.error_for_status_ref() else {
+                unreachable!("500 is an error status");
+            };
+            match resp.json::<ErrorMessage>().await {
+                Ok(err_msg) if err_msg.code == CODE_TOKEN_EXPIRED => {
+                    // Get access token and retry.
+                    self.refresh_access_token("expired").await?;
+                    let resp = self.send_message(msg).await?;
+                    Self::check_response(resp).await
+                }
+                Ok(err_msg) => Err(anyhow::Error::new(status_error).context(err_msg.to_string())),
+                Err(_) => Err(status_error.into()),
+            }
+        } else {
+            Self::check_response(resp).await
+        }
+    }
+
+    /// Keep the code returned by the API in the error, the status error is kept
+    /// as the source so that it can still be retried.
+    async fn check_response(resp: Response) -> Result<()> {
+        let err = match resp.error_for_status_ref() {
+            Ok(_) => return Ok(()),
+            Err(e) => e,
+        };
+        match resp.json::<ErrorMessage>().await {
+            Ok(err_msg) => Err(anyhow::Error::new(err).context(err_msg.to_string())),
+            Err(_) => Err(err.into()),
+        }
+    }
+
     async fn send_message(&self, msg: &Message) -> Result<Response> {
         let resp = self
             .client
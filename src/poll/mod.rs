@@ -0,0 +1,483 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Polling the sources and notifying the new items.
+
+mod poller;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::bail;
+use anyhow::Context;
+use chrono::DateTime;
+use chrono::Local;
+use log::error;
+use log::info;
+use log::warn;
+pub use poller::Poller;
+pub use poller::SHUTDOWN_TIMEOUT;
+use reqwest::Client;
+use tokio_util::sync::CancellationToken;
+
+use crate::error_kind;
+use crate::metrics::metrics;
+use crate::source::try_create_sources;
+use crate::source::Item;
+use crate::source::SourcePtr;
+use crate::state::StatePtr;
+use crate::state::StateStore;
+use crate::AdaptiveInterval;
+use crate::Collapser;
+use crate::Config;
+use crate::DryRunNotifier;
+use crate::EpisodesConfig;
+use crate::Notifier;
+use crate::QQGuildNotifier;
+use crate::QQNotifier;
+use crate::Result;
+
+/// Notifiers by the names of their config sections.
+pub const NOTIFIERS: [&str; 2] = ["qq", "qq_guild"];
+
+/// A polling task, by the config sections of the source and the notifier.
+pub type TaskKey = (&'static str, &'static str);
+
+/// The sources of every task. Each task has its own sources, so that the validators
+/// (`ETag`, `Last-Modified`) and the adaptive interval of a feed follow what its
+/// notifier has been sent. Otherwise the task that pulls second gets `304 Not Modified`
+/// and misses the new items.
+pub fn create_task_sources(config: &Config) -> Result<BTreeMap<TaskKey, SourcePtr>> {
+    let mut sources = BTreeMap::new();
+    for notifier in NOTIFIERS {
+        for (name, source) in create_sources(config)? {
+            sources.insert((name, notifier), source);
+        }
+    }
+    Ok(sources)
+}
+
+pub fn create_sources(config: &Config) -> Result<BTreeMap<&'static str, SourcePtr>> {
+    try_create_sources(config)
+        .into_iter()
+        .map(|(name, source)| {
+            Ok((
+                name,
+                source.with_context(|| format!("Create source {name}"))?,
+            ))
+        })
+        .collect()
+}
+
+/// Changes of the state are not persisted in dry runs.
+pub fn open_state(config: &Config, dry_run: bool) -> Result<StatePtr> {
+    let path = config.state_file.as_deref();
+    if dry_run {
+        StateStore::snapshot(path)
+    } else {
+        StateStore::open(path)
+    }
+}
+
+/// The cursor of the task in the state. Starts from now if the task is new.
+pub fn init_cursor(state: &StatePtr, (source, notifier): TaskKey) -> Result<String> {
+    let cursor = format!("{source}/{notifier}");
+    state.update(|state| {
+        state
+            .cursors
+            .entry(cursor.clone())
+            .or_insert_with(Local::now);
+    })?;
+    Ok(cursor)
+}
+
+/// Poll the source and notify the new items until `shutdown` is cancelled. An
+/// iteration in flight is finished first, so that the state is saved.
+pub async fn run<T: Notifier>(
+    source: SourcePtr,
+    mut notifier: T,
+    config: Arc<Config>,
+    state: StatePtr,
+    cursor: String,
+    shutdown: CancellationToken,
+) {
+    if let Err(e) = source.check_connection().await {
+        error!(
+            source:% = source.name(), kind = error_kind(&e);
+            "Check connection of '{}' failed", source.name()
+        );
+    } else {
+        info!(source:% = source.name(); "Check connection of '{}' successful", source.name());
+    }
+
+    let schedule = source.schedule();
+    let mut adaptive = schedule.adaptive();
+    let mut first = true;
+    let mut collapser = config.collapse.clone().map(|conf| {
+        // the releases held before the task is restarted
+        let held = state.read(|state| state.held.get(&cursor).cloned());
+        let mut collapser = Collapser::new(conf);
+        collapser.restore(held.unwrap_or_default(), Instant::now());
+        collapser
+    });
+
+    loop {
+        if !(first && schedule.immediate()) {
+            let limited = source.feeds().rate_limited_until();
+            let delay = schedule.next_delay(Local::now(), adaptive.as_ref(), limited);
+            metrics().set_gauge(
+                "blooming_poll_interval_seconds",
+                "The delay before the next pull of the source.",
+                &[("source", &source.name()), ("notifier", &notifier.name())],
+                delay.as_secs_f64(),
+            );
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+        first = false;
+
+        let started = Instant::now();
+        let result = poll(
+            &source,
+            &mut notifier,
+            &config,
+            &state,
+            &cursor,
+            collapser.as_mut(),
+            adaptive.as_mut(),
+        )
+        .await;
+
+        let duration_ms = started.elapsed().as_millis() as u64;
+        match result {
+            Ok(0) => {}
+            Ok(items) => info!(
+                source:% = source.name(), notifier:% = notifier.name(), items, duration_ms;
+                "Notified {} new items of '{}'", items, source.name()
+            ),
+            Err(e) => error!(
+                source:% = source.name(),
+                notifier:% = notifier.name(),
+                kind = error_kind(&e),
+                duration_ms;
+                "{}", e
+            ),
+        }
+        if shutdown.is_cancelled() {
+            return;
+        }
+    }
+}
+
+/// Pull the source and deliver the items, returns the number of new items.
+async fn poll<T: Notifier>(
+    source: &SourcePtr,
+    notifier: &mut T,
+    config: &Config,
+    state: &StatePtr,
+    cursor: &str,
+    collapser: Option<&mut Collapser>,
+    adaptive: Option<&mut AdaptiveInterval>,
+) -> Result<usize> {
+    // each feed is retried by the source
+    let items = source.pull_items().await;
+    record_poll(&source.name(), &notifier.name(), items.is_ok());
+    let items = items?;
+
+    // adapt the interval to the publish rate of the feeds
+    if let Some(adaptive) = adaptive {
+        let published = items.iter().map(|item| item.pub_date).collect::<Vec<_>>();
+        let last = adaptive.current();
+        let interval = adaptive.update(&published, source.feeds().hints(), Local::now());
+        if interval != last {
+            info!(
+                source:% = source.name(), interval_ms = interval.as_millis() as u64;
+                "Interval of '{}' changes from {:?} to {:?}", source.name(), last, interval
+            );
+        }
+    }
+
+    let new_items = deliver(source, items, notifier, config, state, cursor, collapser).await?;
+    metrics().inc_counter(
+        "blooming_new_items_total",
+        "Number of new items of a source, before they are collapsed.",
+        &[("source", &source.name()), ("notifier", &notifier.name())],
+        new_items as f64,
+    );
+    Ok(new_items)
+}
+
+/// Each notifier pulls the source in its own task.
+fn record_poll(source: &str, notifier: &str, success: bool) {
+    let result = if success { "success" } else { "failure" };
+    metrics().inc_counter(
+        "blooming_polls_total",
+        "Number of pulls of a source. A pull fails if all feeds of the source fail.",
+        &[
+            ("source", source),
+            ("notifier", notifier),
+            ("result", result),
+        ],
+        1.0,
+    );
+    if success {
+        metrics().set_timestamp(
+            "blooming_last_success_timestamp_seconds",
+            "Unix time of the last successful pull of a source.",
+            &[("source", source), ("notifier", notifier)],
+        );
+    }
+}
+
+/// Notify the items published after the cursor and move the cursor forward. The
+/// releases held by the collapser are saved with the cursor.
+/// Returns the number of new items.
+pub async fn deliver<T: Notifier>(
+    source: &SourcePtr,
+    items: Vec<Item>,
+    notifier: &mut T,
+    config: &Config,
+    state: &StatePtr,
+    cursor: &str,
+    collapser: Option<&mut Collapser>,
+) -> Result<usize> {
+    let (last_update, notified) = state.read(|state| {
+        let notified = state.notified.get(cursor).cloned().unwrap_or_default();
+        (state.cursors.get(cursor).copied(), notified)
+    });
+    let mut new_items = items
+        .into_iter()
+        .filter(|item| last_update.map_or(true, |last| item.pub_date > last))
+        .filter(|item| !notified.contains(&notified_key(item)))
+        .collect::<Vec<_>>();
+    let count = new_items.len();
+    if count > 0 && source.parse_torrent() {
+        attach_torrents(source, &mut new_items).await;
+    }
+
+    // hold releases of the same episode and only notify the preferred one
+    let now = Instant::now();
+    let (mut ready, held) = match collapser {
+        Some(collapser) => {
+            let ready = collapser.collapse(new_items.clone(), now);
+            (ready, Some(collapser.held(now)))
+        }
+        None => (new_items.clone(), None),
+    };
+
+    // move the time marker to the latest item, but not past the items the failed
+    // feeds may have missed, and remember the items notified after it instead
+    let mut ahead = notified.clone();
+    ahead.extend(new_items.iter().map(notified_key));
+    let next = ahead
+        .iter()
+        .map(|(date, _)| *date)
+        .max()
+        .map(|latest| match source.feeds().missing_since() {
+            Some(since) => latest.min(since),
+            None => latest,
+        })
+        .max(last_update);
+    ahead.retain(|(date, _)| next.map_or(true, |next| *date > next));
+
+    // save the held releases with the time marker, as they are behind it
+    let stored = state.read(|state| state.held.contains_key(cursor));
+    if next != last_update || ahead != notified || !ready.is_empty() || (stored && held.is_none()) {
+        let released = state.update(|state| {
+            if let Some(next) = next {
+                state.cursors.insert(cursor.to_string(), next);
+            }
+            if ahead.is_empty() {
+                state.notified.remove(cursor);
+            } else {
+                state.notified.insert(cursor.to_string(), ahead);
+            }
+            match &held {
+                Some(held) if !held.is_empty() => {
+                    state.held.insert(cursor.to_string(), held.clone())
+                }
+                _ => state.held.remove(cursor),
+            }
+        })?;
+        // releases held before the collapser is disabled
+        if held.is_none() {
+            ready.extend(released.into_iter().flatten().flat_map(|held| held.items));
+        }
+    }
+
+    // track episodes and alert missing episodes or complete seasons
+    if let Some(episodes) = &config.episodes {
+        track_episodes(source, notifier, state, episodes, &new_items).await?;
+    }
+
+    // notify
+    if !ready.is_empty() {
+        let each_notify = notifier.num_items_each_notify();
+        if each_notify == 0 {
+            notifier.notify(&source.name(), ready).await?;
+        } else {
+            for chunk in ready.chunks(each_notify) {
+                notifier.notify(&source.name(), chunk.to_vec()).await?;
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+fn notified_key(item: &Item) -> (DateTime<Local>, String) {
+    (item.pub_date, item.title.clone())
+}
+
+async fn attach_torrents(source: &SourcePtr, items: &mut [Item]) {
+    let torrents =
+        futures::future::join_all(items.iter().map(|item| source.fetch_torrent(item))).await;
+    for (item, torrent) in items.iter_mut().zip(torrents) {
+        match torrent {
+            Ok(mut torrent) => {
+                torrent.private |= source.is_private();
+                item.torrent = Some(torrent);
+            }
+            Err(e) => warn!(
+                source:% = source.name(), kind = error_kind(&e);
+                "Parse torrent of '{}' failed: {}", item.title, e
+            ),
+        }
+    }
+}
+
+async fn track_episodes<T: Notifier>(
+    source: &SourcePtr,
+    notifier: &mut T,
+    state: &StatePtr,
+    conf: &EpisodesConfig,
+    items: &[Item],
+) -> Result<()> {
+    let name = notifier.name();
+    let releases = items.iter().filter_map(|item| item.release.as_ref());
+    let events = state.update(|state| state.ledger.observe(&name, releases, &conf.series))?;
+    for event in events {
+        notifier
+            .notify_text(&source.name(), &event.to_string())
+            .await?;
+    }
+    Ok(())
+}
+
+/// Pull the source once, for `blooming fetch`. Returns the items, the latest first.
+pub async fn fetch(config: &Config, name: &str) -> Result<Vec<Item>> {
+    let mut sources = try_create_sources(config);
+    let names = sources.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+    let source = match sources.iter().position(|(n, _)| *n == name) {
+        Some(i) => sources.swap_remove(i).1?,
+        None => bail!(
+            "Source '{}' is not configured, the configured: {}",
+            name,
+            names.join(", ")
+        ),
+    };
+
+    let mut items = source.pull_items().await?;
+    items.sort_by(|a, b| b.pub_date.cmp(&a.pub_date));
+    Ok(items)
+}
+
+/// Pull every source and notify the new items once, with the cursors in the state,
+/// for `blooming once`. Messages are printed instead of sent in dry runs.
+pub async fn once(config: Config, dry_run: bool) -> Result<()> {
+    if config.state_file.is_none() {
+        bail!("`once` needs `state_file` to remember the items notified");
+    }
+    if config.collapse.is_some() {
+        warn!("`collapse` is ignored by `once`, as releases cannot be held between runs");
+    }
+    let sources = create_sources(&config)?;
+    let state = open_state(&config, dry_run)?;
+    let client = Client::new();
+
+    let mut failures = 0;
+    for (&name, source) in &sources {
+        let items = match source.pull_items().await {
+            Ok(items) => items,
+            Err(e) => {
+                error!(
+                    source:% = source.name(), kind = error_kind(&e);
+                    "Pull '{}' failed: {}", source.name(), e
+                );
+                failures += 1;
+                continue;
+            }
+        };
+        for notifier in NOTIFIERS {
+            let key = (name, notifier);
+            let items = items.clone();
+            let result = match notifier {
+                "qq" => match &config.qq {
+                    Some(qq) => {
+                        let notifier = QQNotifier::new(client.clone(), qq.clone());
+                        deliver_once(source, items, notifier, &config, &state, key, dry_run).await
+                    }
+                    None => continue,
+                },
+                "qq_guild" => match &config.qq_guild {
+                    Some(qq_guild) => {
+                        let notifier = QQGuildNotifier::new(client.clone(), qq_guild.clone());
+                        deliver_once(source, items, notifier, &config, &state, key, dry_run).await
+                    }
+                    None => continue,
+                },
+                _ => unreachable!(),
+            };
+            match result {
+                Ok(items) => info!(
+                    source = name, notifier, items;
+                    "Notified {} new items of '{}/{}'", items, name, notifier
+                ),
+                Err(e) => {
+                    error!(
+                        source = name, notifier, kind = error_kind(&e);
+                        "Notify new items of '{}/{}' failed: {}", name, notifier, e
+                    );
+                    failures += 1;
+                }
+            }
+        }
+    }
+
+    if failures > 0 {
+        bail!("{} pulls or notifications failed", failures);
+    }
+    Ok(())
+}
+
+async fn deliver_once<T: Notifier>(
+    source: &SourcePtr,
+    items: Vec<Item>,
+    mut notifier: T,
+    config: &Config,
+    state: &StatePtr,
+    key: TaskKey,
+    dry_run: bool,
+) -> Result<usize> {
+    let cursor = &init_cursor(state, key)?;
+    if dry_run {
+        let mut notifier = DryRunNotifier::new(notifier);
+        deliver(source, items, &mut notifier, config, state, cursor, None).await
+    } else {
+        deliver(source, items, &mut notifier, config, state, cursor, None).await
+    }
+}
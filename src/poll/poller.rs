@@ -0,0 +1,259 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::info;
+use log::warn;
+use reqwest::Client;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use super::create_task_sources;
+use super::init_cursor;
+use super::open_state;
+use super::run;
+use super::TaskKey;
+use crate::logger;
+use crate::source::SourcePtr;
+use crate::state::StatePtr;
+use crate::supervisor::Supervisor;
+use crate::Config;
+use crate::DryRunNotifier;
+use crate::Notifier;
+use crate::QQGuildNotifier;
+use crate::QQNotifier;
+use crate::Result;
+
+/// How long to wait for the in-flight pulls and notifications when stopping tasks.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A running polling task.
+struct Task {
+    handle: JoinHandle<()>,
+    /// Cancelled to stop the task after its in-flight pull.
+    token: CancellationToken,
+}
+
+/// The running sources and their polling tasks.
+pub struct Poller {
+    config: Arc<Config>,
+    state: StatePtr,
+    client: Client,
+    /// The sources of each task, see [`create_task_sources`].
+    sources: BTreeMap<TaskKey, SourcePtr>,
+    tasks: BTreeMap<TaskKey, Task>,
+    /// Print the messages instead of sending them.
+    dry_run: bool,
+    /// The parent of the tokens of the tasks, cancelled on shutdown.
+    shutdown: CancellationToken,
+    /// Restarts the tasks when they crash.
+    supervisor: Supervisor,
+}
+
+impl Poller {
+    pub fn start(config: Config, dry_run: bool) -> Result<Self> {
+        let sources = create_task_sources(&config)?;
+        let state = open_state(&config, dry_run)?;
+        let mut poller = Self {
+            config: Arc::new(config),
+            state,
+            client: Client::new(),
+            sources,
+            tasks: BTreeMap::new(),
+            dry_run,
+            shutdown: CancellationToken::new(),
+            supervisor: Supervisor::default(),
+        };
+        poller.spawn_tasks()?;
+        Ok(poller)
+    }
+
+    /// The running tasks.
+    pub fn tasks(&self) -> Vec<TaskKey> {
+        self.tasks.keys().copied().collect()
+    }
+
+    /// Apply the new config, and only restart the tasks of the changed sources and
+    /// notifiers. The stopped tasks finish their in-flight pulls and notifications
+    /// first. Returns the tasks started.
+    ///
+    /// Keeps running with the old config if the new one is invalid.
+    pub async fn reload(&mut self, config: Config) -> Result<Vec<TaskKey>> {
+        let sources = create_task_sources(&config)?;
+
+        let changed = config.changed_sections(&self.config);
+        if changed.is_empty() {
+            info!("Config is not changed");
+            return Ok(vec![]);
+        }
+        if changed.contains("log") {
+            logger::init(&config.log);
+        }
+        for section in ["state_file", "daemon", "metrics"] {
+            if changed.contains(section) {
+                warn!("Changes of `{}` take effect after restart", section);
+            }
+        }
+        // Shared by all tasks.
+        let restart_all = changed.contains("collapse") || changed.contains("episodes");
+
+        let (stopped, running) =
+            std::mem::take(&mut self.tasks)
+                .into_iter()
+                .partition(|((source, notifier), _)| {
+                    restart_all || changed.contains(*source) || changed.contains(*notifier)
+                });
+        self.tasks = running;
+        for (source, notifier) in stopped.keys() {
+            self.supervisor.remove(&format!("{source}/{notifier}"));
+        }
+        stop(stopped.into_values().collect()).await;
+
+        // Unchanged sources keep the status of their feeds.
+        self.sources = sources
+            .into_iter()
+            .map(|(key, source)| match self.sources.get(&key) {
+                Some(old) if !changed.contains(key.0) => (key, old.clone()),
+                _ => (key, source),
+            })
+            .collect();
+        self.config = Arc::new(config);
+        let started = self.spawn_tasks()?;
+
+        info!(
+            "Reloaded config, changed: {}",
+            changed.into_iter().collect::<Vec<_>>().join(", ")
+        );
+        Ok(started)
+    }
+
+    /// Log the health of each task, on SIGUSR1.
+    pub fn log_health(&self) {
+        for (name, health) in self.supervisor.health() {
+            let status = if health.running {
+                "running"
+            } else {
+                "waiting to restart"
+            };
+            match (health.last_crash, health.last_error) {
+                (Some(time), Some(error)) => warn!(
+                    "Task '{}' is {}, restarted {} times, last crash at {}: {}",
+                    name, status, health.restarts, time, error
+                ),
+                _ => info!("Task '{}' is {}", name, status),
+            }
+        }
+    }
+
+    /// Stop the tasks, waiting for the in-flight notifications so that the state is saved.
+    pub async fn shutdown(self) {
+        self.shutdown.cancel();
+        stop(self.tasks.into_values().collect()).await;
+    }
+
+    /// Spawn the tasks of every source and notifier not running yet, returns the
+    /// tasks spawned.
+    fn spawn_tasks(&mut self) -> Result<Vec<TaskKey>> {
+        let mut started = vec![];
+        for (&key, source) in &self.sources {
+            if self.tasks.contains_key(&key) {
+                continue;
+            }
+            let task = match key.1 {
+                "qq" => self.config.qq.clone().map(|qq| {
+                    let notifier = QQNotifier::new(self.client.clone(), qq);
+                    self.spawn(key, source, notifier)
+                }),
+                "qq_guild" => self.config.qq_guild.clone().map(|qq_guild| {
+                    let notifier = QQGuildNotifier::new(self.client.clone(), qq_guild);
+                    self.spawn(key, source, notifier)
+                }),
+                _ => unreachable!(),
+            };
+            if let Some(task) = task {
+                self.tasks.insert(key, task?);
+                started.push(key);
+            }
+        }
+        Ok(started)
+    }
+
+    fn spawn<T: Notifier + 'static>(
+        &self,
+        key: TaskKey,
+        source: &SourcePtr,
+        notifier: T,
+    ) -> Result<Task> {
+        if self.dry_run {
+            self.supervise(key, source, DryRunNotifier::new(notifier))
+        } else {
+            self.supervise(key, source, notifier)
+        }
+    }
+
+    /// Run the task under the supervisor, which starts it again from the cursor if it crashes.
+    fn supervise<T: Notifier + 'static>(
+        &self,
+        key: TaskKey,
+        source: &SourcePtr,
+        notifier: T,
+    ) -> Result<Task> {
+        let cursor = init_cursor(&self.state, key)?;
+        let source = source.clone();
+        let config = self.config.clone();
+        let state = self.state.clone();
+        let token = self.shutdown.child_token();
+        let shutdown = token.clone();
+        let name = cursor.clone();
+        let start = move || {
+            run(
+                source.clone(),
+                notifier.clone(),
+                config.clone(),
+                state.clone(),
+                cursor.clone(),
+                shutdown.clone(),
+            )
+        };
+        let handle = self.supervisor.spawn(name, token.clone(), start);
+        Ok(Task { handle, token })
+    }
+}
+
+/// Cancel the tasks and wait for them, aborting the tasks not stopped in
+/// [`SHUTDOWN_TIMEOUT`].
+async fn stop(mut tasks: Vec<Task>) {
+    if tasks.is_empty() {
+        return;
+    }
+    for task in &tasks {
+        task.token.cancel();
+    }
+    let handles = futures::future::join_all(tasks.iter_mut().map(|task| &mut task.handle));
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, handles)
+        .await
+        .is_err()
+    {
+        warn!(
+            "Tasks are not stopped in {:?}, abort them",
+            SHUTDOWN_TIMEOUT
+        );
+        for task in &tasks {
+            task.handle.abort();
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod parser;
+
+pub use parser::normalize_name;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Structured information of an anime release, parsed from its title.
+///
+/// Eg. `【豌豆字幕组】[海盗战记 / 冰海战记 第二季 / Vinland_Saga_S2][03][简体][1080P][MP4]`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseInfo {
+    /// The fansub group. Eg. 豌豆字幕组, ANi.
+    pub group: Option<String>,
+    /// Names of the series in different languages.
+    pub titles: Vec<String>,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+    /// Normalized resolution. Eg. 1080P.
+    pub resolution: Option<String>,
+    /// Subtitle language as written in the title. Eg. 简体, CHT, 简日双语.
+    pub subtitle: Option<String>,
+    /// Normalized container format. Eg. MP4, MKV.
+    pub container: Option<String>,
+}
+
+impl ReleaseInfo {
+    /// Parse a release title. Fields that cannot be recognized are left empty.
+    pub fn parse(title: &str) -> Self {
+        parser::parse(title)
+    }
+
+    /// A short description like `ANi 1080P CHT`.
+    pub fn brief(&self) -> String {
+        [&self.group, &self.resolution, &self.subtitle]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Series names normalized for comparison: lowercase, without season
+    /// markers, separators collapsed into single spaces.
+    pub fn series_names(&self) -> Vec<String> {
+        self.titles
+            .iter()
+            .map(|t| normalize_name(t))
+            .filter(|t| !t.is_empty())
+            .collect()
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use super::ReleaseInfo;
+
+const CONTAINERS: &[&str] = &["MP4", "MKV", "AVI", "MOV", "FLV", "WMV", "RMVB"];
+const SUBTITLE_CODES: &[&str] = &["CHS", "CHT", "GB", "BIG5", "JPSC", "JPTC", "SC", "TC"];
+
+enum Token<'a> {
+    /// Content of `[...]`, `【...】`, `(...)` or `（...）`.
+    Bracket(&'a str),
+    /// Text outside of brackets.
+    Text(&'a str),
+}
+
+pub(super) fn parse(title: &str) -> ReleaseInfo {
+    let mut info = ReleaseInfo::default();
+    let mut tokens = tokenize(title);
+
+    // The leading bracket is the fansub group, as long as something follows it.
+    if tokens.len() > 1 {
+        if let Token::Bracket(group) = tokens[0] {
+            info.group = Some(group.to_string());
+            tokens.remove(0);
+        }
+    }
+
+    for token in tokens {
+        match token {
+            Token::Bracket(s) => {
+                if parse_tags(s, &mut info) {
+                    continue;
+                }
+                if info.episode.is_none() {
+                    if let Some(episode) = parse_episode(s) {
+                        info.episode = Some(episode);
+                        continue;
+                    }
+                }
+                if info.titles.is_empty() && !is_decoration(s) {
+                    info.titles = split_titles(s);
+                }
+            }
+            Token::Text(s) => {
+                if is_decoration(s) {
+                    continue;
+                }
+                let take_titles = info.titles.is_empty();
+                // `[ANi] Title S2 - 中文名 第二季 - 03`
+                for part in s.split(" - ").map(str::trim).filter(|p| !p.is_empty()) {
+                    if info.episode.is_none() {
+                        if let Some(episode) = parse_episode(part) {
+                            info.episode = Some(episode);
+                            continue;
+                        }
+                        if let Some(caps) = season_episode_regex().captures(part) {
+                            info.season = caps[1].parse().ok();
+                            info.episode = caps[2].parse().ok();
+                            let rest = season_episode_regex().replace(part, "");
+                            if take_titles {
+                                info.titles.extend(split_titles(rest.trim()));
+                            }
+                            continue;
+                        }
+                    }
+                    if parse_tags(part, &mut info) {
+                        continue;
+                    }
+                    if take_titles {
+                        info.titles.extend(split_titles(part));
+                    }
+                }
+            }
+        }
+    }
+
+    if info.season.is_none() {
+        info.season = info.titles.iter().find_map(|t| parse_season(t));
+    }
+
+    info
+}
+
+/// Lowercase the name, drop the season marker and collapse separators.
+pub fn normalize_name(name: &str) -> String {
+    let name = season_regex().replace_all(name, " ");
+    name.to_lowercase()
+        .split(|c: char| c.is_whitespace() || c == '_' || c == '.')
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parse numbers like `3`, `三`, `十二`, `二十四`.
+fn parse_chinese_number(s: &str) -> Option<u32> {
+    if let Ok(n) = s.parse() {
+        return Some(n);
+    }
+    let mut total = 0;
+    let mut current = 0;
+    for c in s.chars() {
+        match c {
+            '零' | '〇' => current = 0,
+            '十' => {
+                total += current.max(1) * 10;
+                current = 0;
+            }
+            '百' => {
+                total += current.max(1) * 100;
+                current = 0;
+            }
+            _ => current = "一二三四五六七八九".chars().position(|d| d == c)? as u32 + 1,
+        }
+    }
+    Some(total + current)
+}
+
+fn tokenize(title: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = title;
+    while let Some((i, open)) = rest.char_indices().find(|(_, c)| closing(*c).is_some()) {
+        let close = closing(open).unwrap();
+        let start = i + open.len_utf8();
+        let Some(len) = rest[start..].find(close) else {
+            break;
+        };
+        push_text(&mut tokens, &rest[..i]);
+        let inner = rest[start..start + len].trim();
+        if !inner.is_empty() {
+            tokens.push(Token::Bracket(inner));
+        }
+        rest = &rest[start + len + close.len_utf8()..];
+    }
+    push_text(&mut tokens, rest);
+    tokens
+}
+
+fn push_text<'a>(tokens: &mut Vec<Token<'a>>, text: &'a str) {
+    let text = text.trim();
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+}
+
+fn closing(open: char) -> Option<char> {
+    match open {
+        '[' => Some(']'),
+        '【' => Some('】'),
+        '(' => Some(')'),
+        '（' => Some('）'),
+        _ => None,
+    }
+}
+
+/// Things like `★04月新番★`.
+fn is_decoration(s: &str) -> bool {
+    s.contains("新番")
+        || s.chars()
+            .all(|c| matches!(c, '★' | '☆' | '-' | '_') || c.is_whitespace())
+}
+
+fn split_titles(s: &str) -> Vec<String> {
+    s.split(['/', '／'])
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Recognize resolution, subtitle and container tags. Returns true if any is found.
+fn parse_tags(s: &str, info: &mut ReleaseInfo) -> bool {
+    let mut found = false;
+    for word in s.split(|c: char| c.is_whitespace() || c == '_' || c == '+') {
+        if let Some(resolution) = parse_resolution(word) {
+            info.resolution.get_or_insert(resolution);
+            found = true;
+        } else if let Some(container) = CONTAINERS.iter().find(|c| c.eq_ignore_ascii_case(word)) {
+            info.container.get_or_insert(container.to_string());
+            found = true;
+        } else if is_subtitle(word) {
+            info.subtitle.get_or_insert(word.to_string());
+            found = true;
+        }
+    }
+    found
+}
+
+fn parse_resolution(word: &str) -> Option<String> {
+    if word.eq_ignore_ascii_case("4K") {
+        return Some("2160P".to_string());
+    }
+    let caps = resolution_regex().captures(word)?;
+    let height = caps.get(1).or_else(|| caps.get(2))?.as_str();
+    Some(format!("{height}P"))
+}
+
+fn is_subtitle(word: &str) -> bool {
+    SUBTITLE_CODES.iter().any(|c| c.eq_ignore_ascii_case(word))
+        || (word.chars().count() <= 10
+            && ["简", "繁", "中日", "双语"]
+                .iter()
+                .any(|k| word.contains(k)))
+}
+
+fn parse_episode(s: &str) -> Option<u32> {
+    if let Some(caps) = episode_regex().captures(s) {
+        let episode: u32 = caps[1].parse().ok()?;
+        // Years are not episodes.
+        return (episode < 1900).then_some(episode);
+    }
+    let caps = chinese_episode_regex().captures(s)?;
+    parse_chinese_number(&caps[1])
+}
+
+fn parse_season(title: &str) -> Option<u32> {
+    let caps = season_regex().captures(title)?;
+    let season = caps.iter().skip(1).flatten().next()?.as_str();
+    parse_chinese_number(season)
+}
+
+fn resolution_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)^(?:(\d{3,4})p|\d{3,4}x(\d{3,4}))$").unwrap())
+}
+
+fn episode_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(?i)^(?:ep?\.?\s*|#)?(\d{1,4})(?:v\d)?(?:\s*(?:end|fin|完))?$").unwrap()
+    })
+}
+
+fn chinese_episode_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?i)^第\s*(\d+|[零〇一二三四五六七八九十百]+)\s*[话話集](?:\s*(?:end|fin|完))?$",
+        )
+        .unwrap()
+    })
+}
+
+fn season_episode_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)\bS(\d{1,2})E(\d{1,4})(?:v\d)?\b").unwrap())
+}
+
+fn season_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?i)(?:^|[\s_.])S(\d{1,2})(?:$|[\s_.])",
+            r"|第\s*(\d+|[零〇一二三四五六七八九十]+)\s*[季期]",
+            r"|season\s*(\d{1,2})",
+            r"|(\d{1,2})(?:st|nd|rd|th)\s+season",
+        ))
+        .unwrap()
+    })
+}
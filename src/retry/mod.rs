@@ -0,0 +1,144 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use backon::BackoffBuilder;
+use backon::ConstantBackoff;
+use backon::ConstantBuilder;
+use backon::ExponentialBackoff;
+use backon::ExponentialBuilder;
+
+use crate::BackoffKind;
+use crate::RetryConfig;
+
+/// Backoff of retries built from [`RetryConfig`].
+#[derive(Clone, Debug)]
+pub enum RetryPolicy {
+    Constant(ConstantBuilder),
+    /// The builder and the max delay.
+    Exponential(ExponentialBuilder, Duration),
+}
+
+impl RetryPolicy {
+    pub fn new(config: &RetryConfig) -> Self {
+        let delay = config.delay;
+        match config.backoff {
+            BackoffKind::Constant => {
+                let mut builder = ConstantBuilder::default()
+                    .with_delay(delay)
+                    .with_max_times(config.max_times);
+                if config.jitter {
+                    builder = builder.with_jitter();
+                }
+                RetryPolicy::Constant(builder)
+            }
+            BackoffKind::Exponential => {
+                let max_delay = config.max_delay;
+                let mut builder = ExponentialBuilder::default()
+                    .with_min_delay(delay)
+                    .with_max_delay(max_delay)
+                    .with_max_times(config.max_times);
+                if config.jitter {
+                    builder = builder.with_jitter();
+                }
+                RetryPolicy::Exponential(builder, max_delay)
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(&RetryConfig::default())
+    }
+}
+
+impl BackoffBuilder for RetryPolicy {
+    type Backoff = RetryBackoff;
+
+    fn build(&self) -> Self::Backoff {
+        match self {
+            RetryPolicy::Constant(builder) => RetryBackoff::Constant(builder.build()),
+            RetryPolicy::Exponential(builder, max_delay) => {
+                RetryBackoff::Exponential(builder.build(), *max_delay)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RetryBackoff {
+    Constant(ConstantBackoff),
+    Exponential(ExponentialBackoff, Duration),
+}
+
+impl Iterator for RetryBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        match self {
+            RetryBackoff::Constant(backoff) => backoff.next(),
+            // The delay of backon may exceed the max delay by one step.
+            RetryBackoff::Exponential(backoff, max_delay) => {
+                backoff.next().map(|delay| delay.min(*max_delay))
+            }
+        }
+    }
+}
+
+/// Whether the error is temporary and worth retrying.
+///
+/// Timeouts, connection errors, 5xx, 408 and 429 responses are retryable.
+/// Other responses (4xx) and errors (eg. parse errors) are permanent.
+pub fn is_retryable(err: &anyhow::Error) -> bool {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if let Some(status) = e.status() {
+                return status.is_server_error()
+                    || status == reqwest::StatusCode::REQUEST_TIMEOUT
+                    || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+            }
+            return e.is_timeout() || e.is_connect() || e.is_request() || e.is_body();
+        }
+        if cause.is::<std::io::Error>() {
+            return true;
+        }
+    }
+    false
+}
+
+/// The kind of the error for structured logs. Eg. `timeout`, `http_5xx`, `parse`.
+pub fn error_kind(err: &anyhow::Error) -> &'static str {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            return match e.status().map(|status| status.as_u16()) {
+                Some(400..=499) => "http_4xx",
+                Some(500..=599) => "http_5xx",
+                Some(_) => "http",
+                None if e.is_timeout() => "timeout",
+                None if e.is_connect() => "connect",
+                None if e.is_decode() => "parse",
+                None => "request",
+            };
+        }
+        if cause.is::<rss_for_mikan::Error>() || cause.is::<serde_json::Error>() {
+            return "parse";
+        }
+        if cause.is::<std::io::Error>() {
+            return "io";
+        }
+    }
+    "other"
+}
@@ -0,0 +1,300 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::DurationRound;
+use chrono::Local;
+use chrono::NaiveTime;
+use chrono::TimeZone;
+use chrono::Utc;
+use chrono::Weekday;
+
+use crate::source::FeedHints;
+use crate::Result;
+use crate::ScheduleConfig;
+use crate::WindowConfig;
+
+/// The delay after the last time of a cron expression, which never comes again.
+const CRON_ENDED_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The weight of the newest gap between items in the estimated publish rate.
+const GAP_WEIGHT: f64 = 0.25;
+
+/// When to pull a source.
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    interval: Duration,
+    cron: Option<cron::Schedule>,
+    windows: Vec<Window>,
+    jitter: Duration,
+    immediate: bool,
+    /// Min and max intervals of adaptive scheduling.
+    adaptive: Option<(Duration, Duration)>,
+}
+
+/// The interval of adaptive scheduling, kept by each polling task.
+#[derive(Clone, Debug)]
+pub struct AdaptiveInterval {
+    min: Duration,
+    max: Duration,
+    current: Duration,
+    hints: FeedHints,
+    /// Moving average of the gaps between the items, ie. the publish rate.
+    gap: Option<Duration>,
+    /// The latest item seen.
+    last_published: Option<DateTime<Local>>,
+}
+
+/// A time window with its own interval. Eg. airing evenings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Window {
+    /// Any day if empty.
+    days: Vec<Weekday>,
+    start: NaiveTime,
+    end: NaiveTime,
+    interval: Duration,
+}
+
+impl Schedule {
+    /// `interval` is used outside of the windows.
+    pub fn new(interval: Duration, config: &ScheduleConfig) -> Result<Self> {
+        let cron = match &config.cron {
+            Some(expr) => {
+                let cron = cron::Schedule::from_str(expr)
+                    .map_err(|e| anyhow!("Invalid cron expression '{}': {}", expr, e))?;
+                if cron.upcoming(Local).next().is_none() {
+                    bail!("Cron expression '{}' has no upcoming time", expr);
+                }
+                Some(cron)
+            }
+            None => None,
+        };
+        let windows = config
+            .windows
+            .iter()
+            .map(Window::new)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            interval,
+            cron,
+            windows,
+            jitter: config.jitter,
+            immediate: config.immediate,
+            adaptive: config.adaptive.as_ref().map(|a| (a.min, a.max.max(a.min))),
+        })
+    }
+
+    /// Whether to poll on startup instead of waiting for the first delay.
+    pub fn immediate(&self) -> bool {
+        self.immediate
+    }
+
+    /// The interval at `now`, ie. of the first window containing `now`, or the default one.
+    pub fn interval_at(&self, now: DateTime<Local>) -> Duration {
+        self.windows
+            .iter()
+            .find(|w| w.contains(now))
+            .map_or(self.interval, |w| w.interval)
+    }
+
+    /// The delay before the next poll, without jitter.
+    pub fn delay_at(&self, now: DateTime<Local>) -> Duration {
+        if let Some(cron) = &self.cron {
+            return cron
+                .after(&now)
+                .next()
+                .and_then(|next| (next - now).to_std().ok())
+                // the interval may be 0 with cron
+                .unwrap_or(CRON_ENDED_DELAY);
+        }
+
+        let delay = self.interval_at(now);
+        // Do not sleep over the start of a window.
+        match self.next_window_start(now) {
+            Some(start) => (start - now).to_std().map_or(delay, |d| d.min(delay)),
+            None => delay,
+        }
+    }
+
+    /// The adaptive interval starting from the interval, if adaptive scheduling is enabled.
+    pub fn adaptive(&self) -> Option<AdaptiveInterval> {
+        if self.cron.is_some() {
+            return None;
+        }
+        let (min, max) = self.adaptive?;
+        Some(AdaptiveInterval {
+            min,
+            max,
+            current: self.interval.clamp(min, max),
+            hints: FeedHints::default(),
+            gap: None,
+            last_published: None,
+        })
+    }
+
+    /// The delay before the next poll, with a random jitter. Not earlier than
+    /// `rate_limited_until`, when the feeds ask to be pulled later.
+    pub fn next_delay(
+        &self,
+        now: DateTime<Local>,
+        adaptive: Option<&AdaptiveInterval>,
+        rate_limited_until: Option<DateTime<Local>>,
+    ) -> Duration {
+        let delay = match adaptive {
+            Some(adaptive) => adaptive.delay_at(now),
+            None => self.delay_at(now),
+        };
+        let limited = rate_limited_until
+            .and_then(|until| (until - now).to_std().ok())
+            .unwrap_or_default();
+        let jitter = self.jitter.mul_f64(fastrand::f64());
+        delay.max(limited) + jitter
+    }
+
+    /// The earliest start of the windows after `now`, today or tomorrow.
+    fn next_window_start(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        let today = now.date_naive();
+        let tomorrow = today.succ_opt()?;
+        self.windows
+            .iter()
+            .flat_map(|w| {
+                [today, tomorrow]
+                    .into_iter()
+                    .filter(|date| w.matches_day(date.weekday()))
+                    .filter_map(|date| {
+                        Local
+                            .from_local_datetime(&date.and_time(w.start))
+                            .earliest()
+                    })
+            })
+            .filter(|start| *start > now)
+            .min()
+    }
+}
+
+impl AdaptiveInterval {
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// Follow the publish rate estimated from the gaps between the items in the
+    /// feeds, or back off while the feeds stay quiet longer than that. Returns the
+    /// new interval.
+    pub fn update(
+        &mut self,
+        published: &[DateTime<Local>],
+        hints: FeedHints,
+        now: DateTime<Local>,
+    ) -> Duration {
+        let mut dates = published
+            .iter()
+            .copied()
+            .filter(|date| self.last_published.map_or(true, |last| *date > last))
+            .collect::<Vec<_>>();
+        dates.sort();
+        for date in dates {
+            if let Some(last) = self.last_published {
+                let gap = (date - last).to_std().unwrap_or_default();
+                self.gap = Some(match self.gap {
+                    Some(average) => average.mul_f64(1.0 - GAP_WEIGHT) + gap.mul_f64(GAP_WEIGHT),
+                    None => gap,
+                });
+            }
+            self.last_published = Some(date);
+        }
+
+        let next = match self.gap {
+            Some(gap) => {
+                let quiet = self
+                    .last_published
+                    .and_then(|last| (now - last).to_std().ok())
+                    .unwrap_or_default();
+                gap.max(quiet)
+            }
+            // not enough items to estimate
+            None => self.current,
+        };
+        // Never pull faster than the feeds can be cached.
+        let min = hints
+            .ttl
+            .map_or(self.min, |ttl| ttl.max(self.min))
+            .min(self.max);
+        self.current = next.clamp(min, self.max);
+        self.hints = hints;
+        self.current
+    }
+
+    /// The delay before the next poll, postponed out of the skipped hours and days.
+    pub fn delay_at(&self, now: DateTime<Local>) -> Duration {
+        let hour = chrono::Duration::hours(1);
+        let mut next =
+            now.with_timezone(&Utc) + chrono::Duration::from_std(self.current).unwrap_or(hour);
+        // A week at most, in case every hour is skipped.
+        for _ in 0..24 * 7 {
+            if !self.hints.skips(next.with_timezone(&Local)) {
+                break;
+            }
+            // The start of the next hour, skipped hours are in UTC.
+            next = next.duration_trunc(hour).map_or(next, |start| start + hour);
+        }
+        (next - now.with_timezone(&Utc))
+            .to_std()
+            .unwrap_or(self.current)
+    }
+}
+
+impl Window {
+    fn new(config: &WindowConfig) -> Result<Self> {
+        let days = config
+            .days
+            .iter()
+            .map(|day| {
+                Weekday::from_str(day).map_err(|_| anyhow!("Invalid day of the week '{}'", day))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let time = |s: &str| {
+            NaiveTime::parse_from_str(s, "%H:%M")
+                .map_err(|e| anyhow!("Invalid time '{}': {}", s, e))
+        };
+        Ok(Self {
+            days,
+            start: time(&config.start)?,
+            end: time(&config.end)?,
+            interval: config.interval,
+        })
+    }
+
+    fn matches_day(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    /// Windows crossing midnight belong to the day they start.
+    fn contains(&self, now: DateTime<Local>) -> bool {
+        let time = now.time();
+        let day = now.weekday();
+        if self.start <= self.end {
+            self.matches_day(day) && self.start <= time && time < self.end
+        } else {
+            (self.matches_day(day) && time >= self.start)
+                || (self.matches_day(day.pred()) && time < self.end)
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::BufRead;
+
+use crate::release::ReleaseInfo;
+use crate::source::link;
+use crate::source::title_and_date;
+use crate::Item;
+use crate::Result;
+
+pub struct AcgRip;
+
+impl AcgRip {
+    pub fn parse_items<R: BufRead>(content: R) -> Result<Vec<Item>> {
+        let channel = rss_for_mikan::Channel::read_from(content)?;
+
+        Ok(channel
+            .items
+            .into_iter()
+            .filter_map(|item| {
+                let (title, pub_date) = title_and_date("acg.rip", &item)?;
+                let url = link("acg.rip", &title, &item)?;
+                let release = ReleaseInfo::parse(&title);
+                // The items carry no team, take it from the title.
+                Some(Item {
+                    title,
+                    pub_date,
+                    url,
+                    enclosure: item.enclosure.map(|e| e.url),
+                    team: release.group.clone(),
+                    release: Some(release),
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>())
+    }
+
+    /// The name of an acg.rip category id.
+    pub fn category_name(id: u32) -> Option<&'static str> {
+        match id {
+            1 => Some("动画"),
+            2 => Some("日剧"),
+            3 => Some("综艺"),
+            4 => Some("音乐"),
+            5 => Some("合集"),
+            9 => Some("其他"),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use reqwest::Client;
+use reqwest::Url;
+
+use super::AcgRip;
+use crate::source::build_client;
+use crate::source::Feed;
+use crate::source::Feeds;
+use crate::source::Item;
+use crate::source::Source;
+use crate::source::SourcePtr;
+use crate::AcgRipConfig;
+use crate::Result;
+use crate::RetryPolicy;
+use crate::Schedule;
+
+pub struct AcgRipSource {
+    feeds: Feeds,
+    client: Client,
+    category: Option<String>,
+    schedule: Schedule,
+    torrent: bool,
+}
+
+impl AcgRipSource {
+    pub fn try_create(config: &AcgRipConfig) -> Result<SourcePtr> {
+        let base_url = config.base_url.trim_end_matches('/');
+        let base = match (config.team_id, config.category) {
+            (Some(team_id), _) => format!("{base_url}/team/{team_id}.xml"),
+            (None, Some(category)) => format!("{base_url}/{category}.xml"),
+            (None, None) => format!("{base_url}/.xml"),
+        };
+
+        // One feed for each keyword, or the latest releases if there is none.
+        let rsses = if config.keywords.is_empty() {
+            vec![base]
+        } else {
+            config
+                .keywords
+                .iter()
+                .map(|keyword| Ok(Url::parse_with_params(&base, [("term", keyword)])?.to_string()))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        Ok(Arc::new(Self {
+            feeds: Feeds::new(
+                "acg.rip",
+                rsses.into_iter().map(Feed::new).collect(),
+                RetryPolicy::new(&config.retry),
+            ),
+            client: build_client(&config.http)?,
+            category: config
+                .category
+                .and_then(AcgRip::category_name)
+                .map(str::to_string),
+            schedule: Schedule::new(config.interval, &config.schedule)?,
+            torrent: config.torrent,
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl Source for AcgRipSource {
+    fn name(&self) -> String {
+        "acg.rip".to_string()
+    }
+
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    async fn pull_items(&self) -> Result<Vec<Item>> {
+        let mut items = self
+            .feeds
+            .pull(&self.client, |content| AcgRip::parse_items(content))
+            .await?;
+        // The items carry no category, take it from the feed.
+        for item in items.iter_mut() {
+            item.category = self.category.clone();
+        }
+
+        Ok(items)
+    }
+
+    fn client(&self) -> &Client {
+        &self.client
+    }
+
+    fn rsses(&self) -> Vec<String> {
+        self.feeds.urls()
+    }
+
+    fn feeds(&self) -> &Feeds {
+        &self.feeds
+    }
+
+    fn parse_torrent(&self) -> bool {
+        self.torrent
+    }
+}
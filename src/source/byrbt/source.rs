@@ -13,26 +13,44 @@
 // limitations under the License.
 
 use std::sync::Arc;
-use std::time::Duration;
+
+use reqwest::Client;
 
 use super::Byrbt;
+use crate::source::build_client;
+use crate::source::Feed;
+use crate::source::Feeds;
 use crate::source::Item;
 use crate::source::Source;
 use crate::source::SourcePtr;
 use crate::ByrbtConfig;
 use crate::Result;
+use crate::RetryPolicy;
+use crate::Schedule;
 
 pub struct ByrbtSource {
-    rsses: Vec<String>,
-    interval: Duration,
+    feeds: Feeds,
+    client: Client,
+    schedule: Schedule,
+    torrent: bool,
 }
 
 impl ByrbtSource {
-    pub fn create(config: &ByrbtConfig) -> SourcePtr {
-        Arc::new(Self {
-            rsses: config.rsses.clone(),
-            interval: Duration::from_secs(config.interval),
-        })
+    pub fn try_create(config: &ByrbtConfig) -> Result<SourcePtr> {
+        Ok(Arc::new(Self {
+            feeds: Feeds::new(
+                "BYRBT",
+                config
+                    .rsses
+                    .iter()
+                    .map(|rss| Feed::new(rss.expose()))
+                    .collect(),
+                RetryPolicy::new(&config.retry),
+            ),
+            client: build_client(&config.http)?,
+            schedule: Schedule::new(config.interval, &config.schedule)?,
+            torrent: config.torrent,
+        }))
     }
 }
 
@@ -42,31 +60,36 @@ impl Source for ByrbtSource {
         "BYRBT".to_string()
     }
 
-    fn interval(&self) -> Duration {
-        self.interval
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
     }
 
     async fn pull_items(&self) -> Result<Vec<Item>> {
-        let handles = self
-            .rsses
-            .iter()
-            .map(|rss| {
-                let rss = rss.clone();
-                async move {
-                    let content = reqwest::get(rss).await?.bytes().await?;
-                    Byrbt::parse_items(&content[..])
-                }
-            })
-            .collect::<Vec<_>>();
-
-        let contents = futures::future::try_join_all(handles).await?;
-
-        let items = contents.into_iter().flatten().collect::<Vec<_>>();
+        let items = self
+            .feeds
+            .pull(&self.client, |content| Byrbt::parse_items(content))
+            .await?;
 
         Ok(items)
     }
 
+    fn client(&self) -> &Client {
+        &self.client
+    }
+
     fn rsses(&self) -> Vec<String> {
-        self.rsses.clone()
+        self.feeds.urls()
+    }
+
+    fn feeds(&self) -> &Feeds {
+        &self.feeds
+    }
+
+    fn is_private(&self) -> bool {
+        true
+    }
+
+    fn parse_torrent(&self) -> bool {
+        self.torrent
     }
 }
@@ -0,0 +1,56 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::BufRead;
+
+use crate::release::ReleaseInfo;
+use crate::source::link;
+use crate::source::title_and_date;
+use crate::torrent::info_hash_from_magnet;
+use crate::Item;
+use crate::Result;
+
+pub struct Dmhy;
+
+impl Dmhy {
+    pub fn parse_items<R: BufRead>(content: R) -> Result<Vec<Item>> {
+        let channel = rss_for_mikan::Channel::read_from(content)?;
+
+        Ok(channel
+            .items
+            .into_iter()
+            .filter_map(|item| {
+                let (title, pub_date) = title_and_date("dmhy", &item)?;
+                let url = link("dmhy", &title, &item)?;
+                let release = ReleaseInfo::parse(&title);
+                // The enclosure is the magnet link.
+                let magnet = item
+                    .enclosure
+                    .map(|e| e.url)
+                    .filter(|url| url.starts_with("magnet:"));
+                Some(Item {
+                    title,
+                    pub_date,
+                    url,
+                    info_hash: magnet.as_deref().and_then(info_hash_from_magnet),
+                    magnet,
+                    team: item.author.or_else(|| release.group.clone()),
+                    category: item.categories.into_iter().next().map(|c| c.name),
+                    release: Some(release),
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>())
+    }
+}
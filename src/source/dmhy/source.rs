@@ -0,0 +1,110 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use reqwest::Client;
+use reqwest::Url;
+
+use super::Dmhy;
+use crate::source::build_client;
+use crate::source::Feed;
+use crate::source::Feeds;
+use crate::source::Item;
+use crate::source::Source;
+use crate::source::SourcePtr;
+use crate::DmhyConfig;
+use crate::Result;
+use crate::RetryPolicy;
+use crate::Schedule;
+
+pub struct DmhySource {
+    feeds: Feeds,
+    client: Client,
+    schedule: Schedule,
+}
+
+impl DmhySource {
+    pub fn try_create(config: &DmhyConfig) -> Result<SourcePtr> {
+        let base = format!(
+            "{}/topics/rss/rss.xml",
+            config.base_url.trim_end_matches('/')
+        );
+
+        let mut params = vec![];
+        if let Some(sort_id) = config.sort_id {
+            params.push(("sort_id", sort_id.to_string()));
+        }
+        if let Some(team_id) = config.team_id {
+            params.push(("team_id", team_id.to_string()));
+        }
+
+        // One feed for each keyword, or the latest releases if there is none.
+        let rsses = if config.keywords.is_empty() {
+            vec![Url::parse_with_params(&base, &params)?.to_string()]
+        } else {
+            config
+                .keywords
+                .iter()
+                .map(|keyword| {
+                    let mut params = params.clone();
+                    params.insert(0, ("keyword", keyword.clone()));
+                    Ok(Url::parse_with_params(&base, &params)?.to_string())
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        Ok(Arc::new(Self {
+            feeds: Feeds::new(
+                "dmhy",
+                rsses.into_iter().map(Feed::new).collect(),
+                RetryPolicy::new(&config.retry),
+            ),
+            client: build_client(&config.http)?,
+            schedule: Schedule::new(config.interval, &config.schedule)?,
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl Source for DmhySource {
+    fn name(&self) -> String {
+        "dmhy".to_string()
+    }
+
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    async fn pull_items(&self) -> Result<Vec<Item>> {
+        let items = self
+            .feeds
+            .pull(&self.client, |content| Dmhy::parse_items(content))
+            .await?;
+
+        Ok(items)
+    }
+
+    fn client(&self) -> &Client {
+        &self.client
+    }
+
+    fn rsses(&self) -> Vec<String> {
+        self.feeds.urls()
+    }
+
+    fn feeds(&self) -> &Feeds {
+        &self.feeds
+    }
+}
@@ -12,25 +12,50 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::acgrip::AcgRipSource;
 use super::byrbt::ByrbtSource;
+use super::dmhy::DmhySource;
 use super::mikan::MikanSource;
+use super::nyaa::NyaaSource;
 use super::tjupt::TjuptSource;
+use super::torznab::TorznabSource;
 use super::SourcePtr;
 use crate::Config;
 use crate::Result;
 
 pub fn register(factory: &mut SourceFactory, config: &Config) -> Result<()> {
+    for (_, source) in try_create_sources(config) {
+        factory.register(source?);
+    }
+
+    Ok(())
+}
+
+/// Create the configured sources one by one, with the names of their config sections.
+pub fn try_create_sources(config: &Config) -> Vec<(&'static str, Result<SourcePtr>)> {
+    let mut sources = vec![];
     if let Some(config) = &config.mikan {
-        factory.register(MikanSource::try_create(config)?)
+        sources.push(("mikan", MikanSource::try_create(config)));
     }
     if let Some(config) = &config.byrbt {
-        factory.register(ByrbtSource::create(config));
+        sources.push(("byrbt", ByrbtSource::try_create(config)));
     }
     if let Some(config) = &config.tjupt {
-        factory.register(TjuptSource::create(config));
+        sources.push(("tjupt", TjuptSource::try_create(config)));
     }
-
-    Ok(())
+    if let Some(config) = &config.nyaa {
+        sources.push(("nyaa", NyaaSource::try_create(config)));
+    }
+    if let Some(config) = &config.dmhy {
+        sources.push(("dmhy", DmhySource::try_create(config)));
+    }
+    if let Some(config) = &config.acgrip {
+        sources.push(("acgrip", AcgRipSource::try_create(config)));
+    }
+    if let Some(config) = &config.torznab {
+        sources.push(("torznab", TorznabSource::try_create(config)));
+    }
+    sources
 }
 
 #[derive(Default)]
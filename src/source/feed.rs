@@ -0,0 +1,511 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::UNIX_EPOCH;
+
+use anyhow::anyhow;
+use backon::Retryable;
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Local;
+use chrono::Timelike;
+use chrono::Utc;
+use chrono::Weekday;
+use log::debug;
+use log::warn;
+use reqwest::header::HeaderName;
+use reqwest::header::ETAG;
+use reqwest::header::IF_MODIFIED_SINCE;
+use reqwest::header::IF_NONE_MATCH;
+use reqwest::header::LAST_MODIFIED;
+use reqwest::header::RETRY_AFTER;
+use reqwest::Client;
+use reqwest::Response;
+use reqwest::StatusCode;
+
+use crate::error_kind;
+use crate::is_retryable;
+use crate::metrics::metrics;
+use crate::redact_url;
+use crate::Item;
+use crate::Result;
+use crate::RetryPolicy;
+
+/// A RSS feed of a source.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Feed {
+    pub url: String,
+    /// Set to [`Item::label`] of the items from the feed.
+    pub label: Option<String>,
+}
+
+impl Feed {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            label: None,
+        }
+    }
+
+    pub fn with_label(url: impl Into<String>, label: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            label: Some(label.into()),
+        }
+    }
+
+    /// The label, or the url with the secrets masked. Used in logs.
+    pub fn name(&self) -> String {
+        match &self.label {
+            Some(label) => label.clone(),
+            None => redact_url(&self.url),
+        }
+    }
+}
+
+/// Result of the recent pulls of a feed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeedStatus {
+    pub feed: Feed,
+    pub last_success: Option<DateTime<Local>>,
+    pub last_error: Option<String>,
+    /// Number of failed pulls since the last success.
+    pub failures: u32,
+    /// Number of items of the last successful pull.
+    pub items: usize,
+    /// `ETag` of the last response, sent as `If-None-Match`.
+    pub etag: Option<String>,
+    /// `Last-Modified` of the last response, sent as `If-Modified-Since`.
+    pub last_modified: Option<String>,
+    /// The feed is not pulled until then, set by `Retry-After` of 429 or 503 responses,
+    /// or backing off on 429 responses without it.
+    pub retry_after: Option<DateTime<Local>>,
+    /// Polling hints of the last response.
+    pub hints: FeedHints,
+}
+
+/// Polling hints published by a feed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeedHints {
+    /// How long the feed can be cached, from `<ttl>`.
+    pub ttl: Option<Duration>,
+    /// Hours (UTC) the feed need not be pulled, from `<skipHours>`.
+    pub skip_hours: BTreeSet<u32>,
+    /// Days (UTC) the feed need not be pulled, from `<skipDays>`.
+    pub skip_days: HashSet<Weekday>,
+}
+
+impl FeedHints {
+    /// Read the hints from the channel of a RSS. Invalid hints are ignored.
+    pub fn parse(content: &[u8]) -> Self {
+        let Ok(channel) = rss_for_mikan::Channel::read_from(content) else {
+            return Self::default();
+        };
+        Self {
+            // The ttl comes from the feed and may be huge, the adaptive interval
+            // clamps it to the max interval.
+            ttl: channel
+                .ttl
+                .and_then(|ttl| ttl.trim().parse().ok())
+                .map(|minutes: u64| Duration::from_secs(minutes.saturating_mul(60))),
+            skip_hours: channel
+                .skip_hours
+                .iter()
+                .filter_map(|h| h.trim().parse::<u32>().ok())
+                .filter(|h| *h <= 24)
+                // Some feeds use 24 for midnight.
+                .map(|h| h % 24)
+                .collect(),
+            skip_days: channel
+                .skip_days
+                .iter()
+                .filter_map(|d| Weekday::from_str(d.trim()).ok())
+                .collect(),
+        }
+    }
+
+    /// Combine the hints of the feeds of a source: the longest ttl, and only
+    /// the hours and days skipped by every feed.
+    pub fn merge(hints: &[FeedHints]) -> Self {
+        let Some((first, rest)) = hints.split_first() else {
+            return Self::default();
+        };
+        let mut merged = first.clone();
+        for h in rest {
+            merged.ttl = merged.ttl.max(h.ttl);
+            merged.skip_hours.retain(|hour| h.skip_hours.contains(hour));
+            merged.skip_days.retain(|day| h.skip_days.contains(day));
+        }
+        merged
+    }
+
+    /// Whether `time` is in the skipped hours or days.
+    pub fn skips(&self, time: DateTime<Local>) -> bool {
+        let utc = time.with_timezone(&Utc);
+        self.skip_hours.contains(&utc.hour()) || self.skip_days.contains(&utc.weekday())
+    }
+}
+
+/// The longest `Retry-After` honoured, in seconds.
+const MAX_RETRY_AFTER: i64 = 24 * 60 * 60;
+
+/// How long to wait after a 429 without `Retry-After`, doubled after each failure.
+const RATE_LIMIT_DELAY: i64 = 60;
+
+/// The feed asks to be pulled later, until the time of `Retry-After` if any.
+#[derive(Debug)]
+struct RateLimited(Option<DateTime<Local>>);
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(until) => write!(f, "Rate limited until {}", until),
+            None => write!(f, "Rate limited"),
+        }
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Response of a feed. `items` is `None` if the feed is not modified.
+struct Fetched {
+    items: Option<Vec<Item>>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    hints: FeedHints,
+}
+
+/// Feeds of a source, pulled concurrently and tracked separately.
+pub struct Feeds {
+    /// Name of the source, used in the metrics.
+    source: String,
+    status: Mutex<Vec<FeedStatus>>,
+    retry: RetryPolicy,
+}
+
+impl Feeds {
+    pub fn new(source: impl Into<String>, feeds: Vec<Feed>, retry: RetryPolicy) -> Self {
+        let status = feeds
+            .into_iter()
+            .map(|feed| FeedStatus {
+                feed,
+                ..Default::default()
+            })
+            .collect();
+        Self {
+            source: source.into(),
+            status: Mutex::new(status),
+            retry,
+        }
+    }
+
+    pub fn feeds(&self) -> Vec<Feed> {
+        let status = self.status.lock().unwrap();
+        status.iter().map(|s| s.feed.clone()).collect()
+    }
+
+    pub fn urls(&self) -> Vec<String> {
+        let status = self.status.lock().unwrap();
+        status.iter().map(|s| s.feed.url.clone()).collect()
+    }
+
+    pub fn status(&self) -> Vec<FeedStatus> {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Polling hints of all feeds. See [`FeedHints::merge`].
+    pub fn hints(&self) -> FeedHints {
+        let status = self.status.lock().unwrap();
+        let hints = status.iter().map(|s| s.hints.clone()).collect::<Vec<_>>();
+        FeedHints::merge(&hints)
+    }
+
+    /// The time until which every feed is rate limited, the source need not be
+    /// pulled before it. `None` if some feed can be pulled now.
+    pub fn rate_limited_until(&self) -> Option<DateTime<Local>> {
+        let now = Local::now();
+        let status = self.status.lock().unwrap();
+        status
+            .iter()
+            .map(|s| s.retry_after.filter(|until| *until > now))
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .min()
+    }
+
+    /// Items published since then may be missing, as some feeds failed or are rate
+    /// limited: the earliest last success of those feeds, or the epoch if one never
+    /// succeeded. `None` if the last pulls of all feeds succeeded.
+    pub fn missing_since(&self) -> Option<DateTime<Local>> {
+        let status = self.status.lock().unwrap();
+        status
+            .iter()
+            .filter(|s| s.failures > 0)
+            .map(|s| s.last_success.unwrap_or_else(|| UNIX_EPOCH.into()))
+            .min()
+    }
+
+    /// Pull all feeds and parse them with `parse`. Each feed is retried on its own
+    /// if the error is retryable.
+    ///
+    /// Items of the feeds that succeed are returned even if other feeds fail, and
+    /// the same items of several feeds are only returned once.
+    /// Only fails if no feed succeeds and some feed fails. Feeds that are not
+    /// modified since the last pull or are rate limited give no items.
+    pub async fn pull(
+        &self,
+        client: &Client,
+        parse: fn(&[u8]) -> Result<Vec<Item>>,
+    ) -> Result<Vec<Item>> {
+        let now = Local::now();
+        let status = self.status();
+        let handles = status
+            .iter()
+            .map(|status| async move {
+                if matches!(status.retry_after, Some(until) if until > now) {
+                    return None;
+                }
+                let feed = status.feed.name();
+                let labels = [("source", self.source.as_str()), ("feed", feed.as_str())];
+                let fetch = || {
+                    fetch(
+                        client.clone(),
+                        status.feed.url.clone(),
+                        status.etag.clone(),
+                        status.last_modified.clone(),
+                        parse,
+                        &labels,
+                    )
+                };
+                let started = Instant::now();
+                let result = fetch
+                    .retry(&self.retry)
+                    .when(is_retryable)
+                    .notify(|e, delay| {
+                        debug!(
+                            feed:% = feed, kind = error_kind(e);
+                            "Retry feed '{}' in {:?}: {}", feed, delay, e
+                        );
+                        metrics().inc_counter(
+                            "blooming_fetch_retries_total",
+                            "Number of retries of pulling a feed.",
+                            &labels,
+                            1.0,
+                        );
+                    })
+                    .await;
+                let elapsed = started.elapsed();
+                metrics().observe(
+                    "blooming_fetch_duration_seconds",
+                    "Time to pull a feed, including the retries.",
+                    &labels,
+                    elapsed.as_secs_f64(),
+                );
+                if result.is_ok() {
+                    metrics().set_timestamp(
+                        "blooming_feed_last_success_timestamp_seconds",
+                        "Unix time of the last successful pull of a feed.",
+                        &labels,
+                    );
+                }
+                Some((result, elapsed))
+            })
+            .collect::<Vec<_>>();
+        let results = futures::future::join_all(handles).await;
+
+        let mut items = vec![];
+        let mut succeeded = false;
+        let mut last_error = None;
+        let mut status = self.status.lock().unwrap();
+        for (status, result) in status.iter_mut().zip(results) {
+            let Some((result, elapsed)) = result else {
+                continue;
+            };
+            let duration_ms = elapsed.as_millis() as u64;
+            status.retry_after = None;
+            match result {
+                Ok(fetched) => {
+                    succeeded = true;
+                    status.last_success = Some(Local::now());
+                    status.failures = 0;
+                    let Some(feed_items) = fetched.items else {
+                        debug!(
+                            feed:% = status.feed.name(), duration_ms;
+                            "Feed '{}' is not modified", status.feed.name()
+                        );
+                        status.items = 0;
+                        continue;
+                    };
+                    debug!(
+                        feed:% = status.feed.name(), items = feed_items.len(), duration_ms;
+                        "Pulled {} items of feed '{}' in {:?}",
+                        feed_items.len(),
+                        status.feed.name(),
+                        elapsed
+                    );
+                    status.items = feed_items.len();
+                    status.etag = fetched.etag;
+                    status.last_modified = fetched.last_modified;
+                    status.hints = fetched.hints;
+                    items.extend(feed_items.into_iter().map(|mut item| {
+                        item.label = status.feed.label.clone();
+                        item
+                    }));
+                }
+                Err(e) => {
+                    warn!(
+                        feed:% = status.feed.name(), kind = error_kind(&e), duration_ms;
+                        "Pull feed '{}' failed: {}", status.feed.name(), e
+                    );
+                    if let Some(RateLimited(until)) = e.downcast_ref() {
+                        // back off on the rate limits without `Retry-After`
+                        let delay = RATE_LIMIT_DELAY << status.failures.min(20);
+                        let delay = chrono::Duration::seconds(delay.min(MAX_RETRY_AFTER));
+                        status.retry_after = Some(until.unwrap_or_else(|| Local::now() + delay));
+                    }
+                    status.last_error = Some(e.to_string());
+                    status.failures += 1;
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match last_error {
+            Some(e) if !succeeded => Err(anyhow!("All feeds failed, the last error: {}", e)),
+            _ => Ok(dedup(items)),
+        }
+    }
+}
+
+/// The same release may be in several feeds of a source, eg. matching several
+/// keywords or subscriptions. Only the first is kept, with the labels of all.
+fn dedup(items: Vec<Item>) -> Vec<Item> {
+    let mut deduped: Vec<Item> = Vec::with_capacity(items.len());
+    let mut seen = HashMap::new();
+    for item in items {
+        let key = match (&item.info_hash, item.url.is_empty()) {
+            (Some(hash), _) => hash.to_lowercase(),
+            (None, false) => item.url.clone(),
+            (None, true) => format!("{}@{}", item.title, item.pub_date),
+        };
+        let Some(&index) = seen.get(&key) else {
+            seen.insert(key, deduped.len());
+            deduped.push(item);
+            continue;
+        };
+        if let (Some(label), Some(other)) = (&mut deduped[index].label, item.label) {
+            if !label.split(", ").any(|l| l == other) {
+                label.push_str(", ");
+                label.push_str(&other);
+            }
+        }
+    }
+    deduped
+}
+
+async fn fetch(
+    client: Client,
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    parse: fn(&[u8]) -> Result<Vec<Item>>,
+    labels: &[(&str, &str)],
+) -> Result<Fetched> {
+    let mut request = client.get(url);
+    if let Some(etag) = &etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+    // Urls of PT sites contain passkeys, so they are dropped from the errors.
+    let response = request.send().await.map_err(reqwest::Error::without_url)?;
+
+    let status = response.status();
+    let class = format!("{}xx", status.as_u16() / 100);
+    metrics().inc_counter(
+        "blooming_http_responses_total",
+        "Number of responses of the feeds by status class, eg. 2xx, 5xx.",
+        &[labels, &[("class", &class)]].concat(),
+        1.0,
+    );
+    if status == StatusCode::NOT_MODIFIED {
+        return Ok(Fetched {
+            items: None,
+            etag,
+            last_modified,
+            hints: FeedHints::default(),
+        });
+    }
+    if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+        let until = header(&response, RETRY_AFTER).and_then(|v| parse_retry_after(&v));
+        // 503 without `Retry-After` is usually a transient error and is retried
+        if until.is_some() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(RateLimited(until).into());
+        }
+    }
+
+    let response = response
+        .error_for_status()
+        .map_err(reqwest::Error::without_url)?;
+    let etag = header(&response, ETAG);
+    let last_modified = header(&response, LAST_MODIFIED);
+    let content = response
+        .bytes()
+        .await
+        .map_err(reqwest::Error::without_url)?;
+    let items = parse(&content[..]).map_err(|e| {
+        metrics().inc_counter(
+            "blooming_parse_errors_total",
+            "Number of responses of the feeds that fail to parse.",
+            labels,
+            1.0,
+        );
+        e
+    })?;
+    Ok(Fetched {
+        items: Some(items),
+        etag,
+        last_modified,
+        hints: FeedHints::parse(&content[..]),
+    })
+}
+
+fn header(response: &Response, name: HeaderName) -> Option<String> {
+    let value = response.headers().get(name)?.to_str().ok()?;
+    Some(value.to_string())
+}
+
+/// `Retry-After` is either seconds or a HTTP date. It is clamped to
+/// [`MAX_RETRY_AFTER`] so that a feed cannot pause itself for good.
+fn parse_retry_after(value: &str) -> Option<DateTime<Local>> {
+    let value = value.trim();
+    let now = Local::now();
+    let max = now + chrono::Duration::seconds(MAX_RETRY_AFTER);
+    if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+        // Too many digits for i64 is too long anyway.
+        let seconds = value.parse::<i64>().unwrap_or(MAX_RETRY_AFTER);
+        return Some(now + chrono::Duration::seconds(seconds.min(MAX_RETRY_AFTER)));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(date.with_timezone(&Local).min(max))
+}
@@ -0,0 +1,67 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderName;
+use reqwest::header::HeaderValue;
+use reqwest::header::COOKIE;
+use reqwest::Certificate;
+use reqwest::Client;
+use reqwest::Proxy;
+
+use crate::HttpConfig;
+use crate::Result;
+
+/// Timeout of requests if not configured.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Build the HTTP client of a source.
+pub fn build_client(config: &HttpConfig) -> Result<Client> {
+    let timeout = config.timeout.unwrap_or(DEFAULT_TIMEOUT);
+    let mut builder = Client::builder()
+        .timeout(timeout)
+        .cookie_store(config.cookie_store)
+        .danger_accept_invalid_certs(config.insecure);
+
+    // `Proxy::all` also supports socks5:// and socks5h:// proxies.
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(Proxy::all(proxy.expose())?);
+    }
+    if let Some(user_agent) = &config.user_agent {
+        builder = builder.user_agent(user_agent);
+    }
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in config.headers.iter() {
+        let name =
+            HeaderName::from_str(name).map_err(|e| anyhow!("Invalid header '{}': {}", name, e))?;
+        headers.insert(name, HeaderValue::from_str(value.expose())?);
+    }
+    if let Some(cookie) = &config.cookie {
+        headers.insert(COOKIE, HeaderValue::from_str(cookie.expose())?);
+    }
+    builder = builder.default_headers(headers);
+
+    if let Some(ca_file) = &config.ca_file {
+        let pem = std::fs::read(ca_file)
+            .map_err(|e| anyhow!("Read CA certificate '{}' failed: {}", ca_file, e))?;
+        builder = builder.add_root_certificate(Certificate::from_pem(&pem)?);
+    }
+
+    Ok(builder.build()?)
+}
@@ -12,13 +12,180 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
+
 use chrono::DateTime;
 use chrono::Local;
+use log::warn;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::release::ReleaseInfo;
+use crate::torrent::TorrentInfo;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Item {
     pub title: String,
     /// Torrent download url.
     pub url: String,
     pub pub_date: DateTime<Local>,
+    /// Enclosure url published by the feed, usually the torrent file.
+    ///
+    /// Not serialized, as the download links of PT sites contain passkeys.
+    #[serde(skip)]
+    pub enclosure: Option<String>,
+    /// The subscription of the source that the item comes from. Eg. a Mikan bangumi.
+    pub label: Option<String>,
+    /// Content length published by the feed. (bytes)
+    pub size: Option<u64>,
+    /// Magnet link published by the feed.
+    pub magnet: Option<String>,
+    /// BitTorrent v1 info hash published by the feed (hex).
+    pub info_hash: Option<String>,
+    /// The team (fansub group) that published the item.
+    pub team: Option<String>,
+    /// Category published by the feed. Eg. Anime - English-translated.
+    pub category: Option<String>,
+    pub seeders: Option<u64>,
+    pub leechers: Option<u64>,
+    /// Number of completed downloads.
+    pub downloads: Option<u64>,
+    /// Other site specific attributes. Eg. `trusted` of Nyaa.
+    pub attrs: BTreeMap<String, String>,
+    /// The parsed torrent file, only set if the source parses torrents.
+    pub torrent: Option<TorrentInfo>,
+    /// Release information parsed from the title, only set by anime sources.
+    pub release: Option<ReleaseInfo>,
+    /// Other releases of the same episode, only set in collapse mode.
+    pub alternatives: Vec<Item>,
+}
+
+impl Item {
+    /// The url to download the torrent file.
+    pub fn torrent_url(&self) -> &str {
+        self.enclosure.as_deref().unwrap_or(&self.url)
+    }
+
+    /// The magnet link of the item, built from the parsed torrent, or the magnet
+    /// link or the info hash published by the feed.
+    pub fn magnet(&self) -> Option<String> {
+        if let Some(torrent) = &self.torrent {
+            return torrent.magnet();
+        }
+        if let Some(magnet) = &self.magnet {
+            return Some(magnet.clone());
+        }
+        let torrent = TorrentInfo {
+            info_hash_v1: Some(self.info_hash.clone()?),
+            name: self.title.clone(),
+            ..Default::default()
+        };
+        torrent.magnet()
+    }
+
+    /// A compact list of the alternatives. Eg. `ANi 1080P CHT | LoliHouse 1080P 简繁`.
+    pub fn alternatives_brief(&self) -> Option<String> {
+        if self.alternatives.is_empty() {
+            return None;
+        }
+        let briefs = self
+            .alternatives
+            .iter()
+            .map(|alt| match alt.release.as_ref().map(ReleaseInfo::brief) {
+                Some(brief) if !brief.is_empty() => brief,
+                _ => alt.title.clone(),
+            })
+            .collect::<Vec<_>>();
+        Some(briefs.join(" | "))
+    }
+
+    /// The total size of the item, preferring the parsed torrent over the feed.
+    pub fn total_size(&self) -> Option<u64> {
+        self.torrent.as_ref().map(|t| t.total_size).or(self.size)
+    }
+}
+
+/// The title and the publish date, which every item of a feed must have.
+///
+/// Returns `None` for malformed items of third-party feeds, which are skipped with a
+/// warning instead of failing the whole feed.
+pub(crate) fn title_and_date(
+    source: &str,
+    item: &rss_for_mikan::Item,
+) -> Option<(String, DateTime<Local>)> {
+    let date = item
+        .pub_date
+        .as_deref()
+        .and_then(|date| DateTime::parse_from_rfc2822(date.trim()).ok());
+    match (&item.title, date) {
+        (Some(title), Some(date)) => Some((title.clone(), date.with_timezone(&Local))),
+        (Some(title), None) => {
+            warn!(
+                source;
+                "Skip item '{}' of '{}' without a valid date: {:?}", title, source, item.pub_date
+            );
+            None
+        }
+        (None, _) => {
+            warn!(source; "Skip an item of '{}' without a title", source);
+            None
+        }
+    }
+}
+
+/// The link of an item, see [`title_and_date`].
+pub(crate) fn link(source: &str, title: &str, item: &rss_for_mikan::Item) -> Option<String> {
+    if item.link.is_none() {
+        warn!(source; "Skip item '{}' of '{}' without a link", title, source);
+    }
+    item.link.clone()
+}
+
+/// Parse sizes like `1.4 GiB`, `823.5 MiB` or `433.75 MB` into bytes.
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let unit = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1u64,
+        "KB" | "KIB" => 1 << 10,
+        "MB" | "MIB" => 1 << 20,
+        "GB" | "GIB" => 1 << 30,
+        "TB" | "TIB" => 1 << 40,
+        _ => return None,
+    };
+    Some((number * unit as f64) as u64)
+}
+
+/// Format bytes like `1.40 GiB`, the reverse of [`parse_size`].
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{:.2} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size() {
+        assert_eq!(parse_size("433.75 MB"), Some(454819840));
+        assert_eq!(parse_size("12 KiB"), Some(12288));
+        assert_eq!(parse_size("1.5 XB"), None);
+        assert_eq!(format_size(454819840), "433.75 MiB");
+        assert_eq!(format_size(1000), "1000 B");
+    }
 }
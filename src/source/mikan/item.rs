@@ -16,7 +16,9 @@ use std::io::BufRead;
 
 use chrono::DateTime;
 use chrono::Local;
+use log::warn;
 
+use crate::release::ReleaseInfo;
 use crate::Item;
 use crate::Result;
 
@@ -29,17 +31,39 @@ impl Mikan {
         Ok(channel
             .items
             .into_iter()
-            .map(|item| {
-                let mut date = item.torrent.unwrap().pub_date.unwrap();
-                date.push_str("+08:00");
-                let pub_date = DateTime::parse_from_rfc3339(&date)
-                    .unwrap()
-                    .with_timezone(&Local {});
-                Item {
-                    title: item.title.unwrap(),
-                    pub_date,
-                    url: item.enclosure.unwrap().url,
-                }
+            .filter_map(|item| {
+                let Some(title) = item.title.clone() else {
+                    warn!(source = "Mikan"; "Skip an item of 'Mikan' without a title");
+                    return None;
+                };
+                // Mikan publishes the date in its own extension, in UTC+8 without the offset.
+                let date = item.torrent.as_ref().and_then(|t| t.pub_date.as_deref());
+                let Some(pub_date) = date.and_then(|date| {
+                    DateTime::parse_from_rfc3339(&format!("{}+08:00", date.trim())).ok()
+                }) else {
+                    warn!(
+                        source = "Mikan";
+                        "Skip item '{}' of 'Mikan' without a valid date: {:?}", title, date
+                    );
+                    return None;
+                };
+                let Some(enclosure) = item.enclosure else {
+                    warn!(source = "Mikan"; "Skip item '{}' of 'Mikan' without a torrent", title);
+                    return None;
+                };
+                // The description is the title with more tags (and the size) appended.
+                let release = match &item.description {
+                    Some(desc) if desc.starts_with(&title) => ReleaseInfo::parse(desc),
+                    _ => ReleaseInfo::parse(&title),
+                };
+                Some(Item {
+                    title,
+                    pub_date: pub_date.with_timezone(&Local),
+                    url: enclosure.url,
+                    size: enclosure.length.parse().ok(),
+                    release: Some(release),
+                    ..Default::default()
+                })
             })
             .collect::<Vec<_>>())
     }
@@ -13,42 +13,84 @@
 // limitations under the License.
 
 use std::sync::Arc;
-use std::time::Duration;
 
 use reqwest::Client;
-use reqwest::Proxy;
+use reqwest::Url;
 
 use super::Mikan;
+use crate::source::build_client;
+use crate::source::Feed;
+use crate::source::Feeds;
 use crate::source::Item;
 use crate::source::Source;
 use crate::source::SourcePtr;
 use crate::MikanConfig;
 use crate::Result;
+use crate::RetryPolicy;
+use crate::Schedule;
 
 pub struct MikanSource {
-    rss: String,
-    interval: Duration,
+    feeds: Feeds,
+    schedule: Schedule,
     client: Client,
+    torrent: bool,
 }
 
 impl MikanSource {
     pub fn try_create(config: &MikanConfig) -> Result<SourcePtr> {
-        let mut builder = Client::builder().timeout(Duration::from_secs(2));
-
-        if let Some(proxy) = &config.proxy {
-            builder = builder
-                .proxy(Proxy::http(proxy)?)
-                .proxy(Proxy::https(proxy)?)
+        let mut http = config.http.clone();
+        if http.proxy.is_none() {
+            http.proxy = config.proxy.clone();
         }
-
-        let client = builder.build()?;
+        let client = build_client(&http)?;
 
         Ok(Arc::new(Self {
-            rss: config.rss.clone(),
-            interval: Duration::from_secs(config.interval),
+            feeds: Feeds::new(
+                "Mikan",
+                Self::feeds(config)?,
+                RetryPolicy::new(&config.retry),
+            ),
+            schedule: Schedule::new(config.interval, &config.schedule)?,
             client,
+            torrent: config.torrent,
         }))
     }
+
+    fn feeds(config: &MikanConfig) -> Result<Vec<Feed>> {
+        let base_url = config.base_url.trim_end_matches('/');
+        let mut feeds = vec![];
+
+        for rss in config.rss.iter().chain(config.rsses.iter()) {
+            feeds.push(Feed::with_label(rss.expose(), "MyBangumi"));
+        }
+        for bangumi in config.bangumis.iter() {
+            let mut params = vec![("bangumiId", bangumi.id.to_string())];
+            if let Some(subgroup) = bangumi.subgroup {
+                params.push(("subgroupid", subgroup.to_string()));
+            }
+            let url = Url::parse_with_params(&format!("{base_url}/RSS/Bangumi"), &params)?;
+            let label = bangumi
+                .label
+                .clone()
+                .unwrap_or_else(|| format!("Bangumi {}", bangumi.id));
+            feeds.push(Feed::with_label(url, label));
+        }
+        for keyword in config.keywords.iter() {
+            let url = Url::parse_with_params(&format!("{base_url}/RSS/Search"), [(
+                "searchstr",
+                keyword,
+            )])?;
+            feeds.push(Feed::with_label(url, format!("Search {keyword}")));
+        }
+        if config.classic {
+            feeds.push(Feed::with_label(
+                format!("{base_url}/RSS/Classic"),
+                "Classic",
+            ));
+        }
+
+        Ok(feeds)
+    }
 }
 
 #[async_trait::async_trait]
@@ -57,21 +99,32 @@ impl Source for MikanSource {
         "Mikan".to_string()
     }
 
-    fn interval(&self) -> Duration {
-        self.interval
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
     }
 
     async fn pull_items(&self) -> Result<Vec<Item>> {
-        let content = self.client.get(&self.rss).send().await?.bytes().await?;
-        Mikan::parse_items(&content[..])
+        let items = self
+            .feeds
+            .pull(&self.client, |content| Mikan::parse_items(content))
+            .await?;
+
+        Ok(items)
     }
 
-    async fn check_connection(&self) -> Result<()> {
-        self.client.get(&self.rss).send().await?;
-        Ok(())
+    fn client(&self) -> &Client {
+        &self.client
     }
 
     fn rsses(&self) -> Vec<String> {
-        vec![self.rss.clone()]
+        self.feeds.urls()
+    }
+
+    fn feeds(&self) -> &Feeds {
+        &self.feeds
+    }
+
+    fn parse_torrent(&self) -> bool {
+        self.torrent
     }
 }
@@ -12,23 +12,46 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod acgrip;
 mod byrbt;
+mod dmhy;
 mod factory;
+mod feed;
+mod http;
 mod item;
 mod mikan;
+mod nyaa;
 mod tjupt;
+mod torznab;
 
 use std::sync::Arc;
-use std::time::Duration;
 
+pub use acgrip::AcgRip;
 pub use byrbt::Byrbt;
+pub use dmhy::Dmhy;
 pub use factory::register;
+pub use factory::try_create_sources;
 pub use factory::SourceFactory;
+pub use feed::Feed;
+pub use feed::FeedHints;
+pub use feed::FeedStatus;
+pub use feed::Feeds;
+pub use http::build_client;
+pub use item::format_size;
+use item::link;
+pub use item::parse_size;
+use item::title_and_date;
 pub use item::Item;
 pub use mikan::Mikan;
+pub use nyaa::Nyaa;
+use reqwest::Client;
 pub use tjupt::Tjupt;
+pub use torznab::Torznab;
 
+use crate::torrent;
+use crate::torrent::TorrentInfo;
 use crate::Result;
+use crate::Schedule;
 
 #[async_trait::async_trait]
 pub trait Source: Send + Sync {
@@ -36,19 +59,41 @@ pub trait Source: Send + Sync {
     fn name(&self) -> String;
     /// Pull items from the source.
     async fn pull_items(&self) -> Result<Vec<Item>>;
-    /// The time interval between two pulls.
-    fn interval(&self) -> Duration;
+    /// When to pull the source.
+    fn schedule(&self) -> &Schedule;
+    /// The HTTP client of the source, built from its `http` options.
+    fn client(&self) -> &Client;
     /// Get RSS subscription links.
     fn rsses(&self) -> Vec<String>;
+    /// The RSS feeds and their status after the recent pulls.
+    fn feeds(&self) -> &Feeds;
+    /// Whether the source is a private tracker (PT).
+    ///
+    /// Torrents from private trackers are always treated as private so that
+    /// passkeys in their trackers will not leak into magnet links.
+    fn is_private(&self) -> bool {
+        false
+    }
+    /// Whether to download and parse the torrent files of new items.
+    fn parse_torrent(&self) -> bool {
+        false
+    }
+    /// Download and parse the torrent file of the item.
+    async fn fetch_torrent(&self, item: &Item) -> Result<TorrentInfo> {
+        torrent::fetch(self.client(), item.torrent_url()).await
+    }
     /// Check connection to the RSS source.
     async fn check_connection(&self) -> Result<()> {
         let handles = self
             .rsses()
             .into_iter()
-            .map(|rss| async move { reqwest::get(&rss).await })
+            .map(|rss| self.client().get(rss).send())
             .collect::<Vec<_>>();
 
-        futures::future::try_join_all(handles).await?;
+        // the urls may contain passkeys
+        futures::future::try_join_all(handles)
+            .await
+            .map_err(reqwest::Error::without_url)?;
 
         Ok(())
     }
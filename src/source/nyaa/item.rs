@@ -0,0 +1,70 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::io::BufRead;
+
+use rss_for_mikan::extension::ExtensionMap;
+
+use crate::release::ReleaseInfo;
+use crate::source::link;
+use crate::source::parse_size;
+use crate::source::title_and_date;
+use crate::Item;
+use crate::Result;
+
+pub struct Nyaa;
+
+impl Nyaa {
+    pub fn parse_items<R: BufRead>(content: R) -> Result<Vec<Item>> {
+        let channel = rss_for_mikan::Channel::read_from(content)?;
+
+        Ok(channel
+            .items
+            .into_iter()
+            .filter_map(|item| {
+                let (title, pub_date) = title_and_date("Nyaa", &item)?;
+                let url = link("Nyaa", &title, &item)?;
+                let ext = |name| nyaa_ext(&item.extensions, name);
+                let count = |name| ext(name).and_then(|v| v.parse().ok());
+
+                let mut attrs = BTreeMap::new();
+                for name in ["categoryId", "trusted", "remake"] {
+                    if let Some(value) = ext(name) {
+                        attrs.insert(name.to_string(), value.to_string());
+                    }
+                }
+
+                Some(Item {
+                    release: Some(ReleaseInfo::parse(&title)),
+                    title,
+                    pub_date,
+                    url,
+                    size: ext("size").and_then(parse_size),
+                    info_hash: ext("infoHash").map(|h| h.to_lowercase()),
+                    category: ext("category").map(str::to_string),
+                    seeders: count("seeders"),
+                    leechers: count("leechers"),
+                    downloads: count("downloads"),
+                    attrs,
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>())
+    }
+}
+
+fn nyaa_ext<'a>(extensions: &'a ExtensionMap, name: &str) -> Option<&'a str> {
+    extensions.get("nyaa")?.get(name)?.first()?.value.as_deref()
+}
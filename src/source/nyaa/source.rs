@@ -0,0 +1,110 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use reqwest::Client;
+use reqwest::Url;
+
+use super::Nyaa;
+use crate::source::build_client;
+use crate::source::Feed;
+use crate::source::Feeds;
+use crate::source::Item;
+use crate::source::Source;
+use crate::source::SourcePtr;
+use crate::NyaaConfig;
+use crate::Result;
+use crate::RetryPolicy;
+use crate::Schedule;
+
+pub struct NyaaSource {
+    feeds: Feeds,
+    client: Client,
+    schedule: Schedule,
+    torrent: bool,
+}
+
+impl NyaaSource {
+    pub fn try_create(config: &NyaaConfig) -> Result<SourcePtr> {
+        let base = format!("{}/", config.base_url.trim_end_matches('/'));
+        let filter = config.filter.to_string();
+
+        // One feed for each category.
+        let categories = if config.categories.is_empty() {
+            vec!["0_0".to_string()]
+        } else {
+            config.categories.clone()
+        };
+        let rsses = categories
+            .iter()
+            .map(|category| {
+                let url = Url::parse_with_params(&base, [
+                    ("page", "rss"),
+                    ("q", &config.query),
+                    ("c", category),
+                    ("f", &filter),
+                ])?;
+                Ok(url.to_string())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Arc::new(Self {
+            feeds: Feeds::new(
+                "Nyaa",
+                rsses.into_iter().map(Feed::new).collect(),
+                RetryPolicy::new(&config.retry),
+            ),
+            client: build_client(&config.http)?,
+            schedule: Schedule::new(config.interval, &config.schedule)?,
+            torrent: config.torrent,
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl Source for NyaaSource {
+    fn name(&self) -> String {
+        "Nyaa".to_string()
+    }
+
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    async fn pull_items(&self) -> Result<Vec<Item>> {
+        let items = self
+            .feeds
+            .pull(&self.client, |content| Nyaa::parse_items(content))
+            .await?;
+
+        Ok(items)
+    }
+
+    fn client(&self) -> &Client {
+        &self.client
+    }
+
+    fn rsses(&self) -> Vec<String> {
+        self.feeds.urls()
+    }
+
+    fn feeds(&self) -> &Feeds {
+        &self.feeds
+    }
+
+    fn parse_torrent(&self) -> bool {
+        self.torrent
+    }
+}
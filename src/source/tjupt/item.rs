@@ -14,9 +14,8 @@
 
 use std::io::BufRead;
 
-use chrono::DateTime;
-use chrono::Local;
-
+use crate::source::link;
+use crate::source::title_and_date;
 use crate::Item;
 use crate::Result;
 
@@ -29,16 +28,17 @@ impl Tjupt {
         Ok(channel
             .items
             .into_iter()
-            .map(|item| {
-                let date = item.pub_date.unwrap();
-                let pub_date = DateTime::parse_from_rfc2822(&date)
-                    .unwrap()
-                    .with_timezone(&Local {});
-                Item {
-                    title: item.title.unwrap(),
+            .filter_map(|item| {
+                let (title, pub_date) = title_and_date("Tjupt", &item)?;
+                let url = link("Tjupt", &title, &item)?;
+                Some(Item {
+                    title,
                     pub_date,
-                    url: item.link.unwrap(),
-                }
+                    url,
+                    size: item.enclosure.as_ref().and_then(|e| e.length.parse().ok()),
+                    enclosure: item.enclosure.map(|e| e.url),
+                    ..Default::default()
+                })
             })
             .collect::<Vec<_>>())
     }
@@ -0,0 +1,132 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::sync::OnceLock;
+
+use anyhow::anyhow;
+use regex::Regex;
+use rss_for_mikan::extension::ExtensionMap;
+
+use crate::has_secrets;
+use crate::release::ReleaseInfo;
+use crate::source::title_and_date;
+use crate::torrent::strip_trackers;
+use crate::Item;
+use crate::Result;
+
+pub struct Torznab;
+
+impl Torznab {
+    pub fn parse_items<R: BufRead>(mut content: R) -> Result<Vec<Item>> {
+        let mut buf = Vec::new();
+        content.read_to_end(&mut buf)?;
+
+        // Errors are returned as `<error code="100" description="Invalid API Key"/>`.
+        if let Some(caps) = error_regex().captures(&String::from_utf8_lossy(&buf)) {
+            return Err(anyhow!("Torznab error {}: {}", &caps[1], &caps[2]));
+        }
+
+        let channel = rss_for_mikan::Channel::read_from(&buf[..])?;
+
+        Ok(channel
+            .items
+            .into_iter()
+            .filter_map(|item| {
+                let (title, pub_date) = title_and_date("Torznab", &item)?;
+                let attrs = torznab_attrs(&item.extensions);
+                let count = |name: &str| attrs.get(name).and_then(|v| v.parse::<u64>().ok());
+
+                // The link is either the torrent file or the magnet link. The indexer may
+                // be private, so the trackers with the passkey are dropped from the magnet.
+                let link = item.link.clone().unwrap_or_default();
+                let magnet = attrs
+                    .get("magneturl")
+                    .cloned()
+                    .or_else(|| link.starts_with("magnet:").then(|| link.clone()))
+                    .map(|magnet| strip_trackers(&magnet));
+                let enclosure = item
+                    .enclosure
+                    .as_ref()
+                    .map(|e| e.url.clone())
+                    .or_else(|| (!link.is_empty()).then(|| link.clone()))
+                    .filter(|url| !url.starts_with("magnet:"));
+                // The url is sent to the notifiers. The link is the download link of
+                // Jackett or Prowlarr with the api key, so it is never used.
+                let url = [
+                    item.comments.clone(),
+                    item.guid.as_ref().map(|g| g.value.clone()),
+                ]
+                .into_iter()
+                .flatten()
+                .find(|url| *url != link && url.starts_with("http") && !has_secrets(url))
+                .unwrap_or_default();
+
+                // `peers` includes the seeders.
+                let seeders = count("seeders");
+                let leechers =
+                    count("peers").map(|peers| peers.saturating_sub(seeders.unwrap_or(0)));
+
+                Some(Item {
+                    release: Some(ReleaseInfo::parse(&title)),
+                    title,
+                    pub_date,
+                    url,
+                    enclosure,
+                    size: count("size").or_else(|| {
+                        item.enclosure
+                            .as_ref()
+                            .and_then(|e| e.length.parse().ok())
+                            .filter(|length| *length > 0)
+                    }),
+                    magnet,
+                    info_hash: attrs.get("infohash").map(|h| h.to_lowercase()),
+                    category: item.categories.first().map(|c| c.name.clone()),
+                    seeders,
+                    leechers,
+                    downloads: count("grabs"),
+                    attrs,
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>())
+    }
+}
+
+/// Collect `<torznab:attr name="..." value="..."/>`. Repeated attributes are joined by ','.
+fn torznab_attrs(extensions: &ExtensionMap) -> BTreeMap<String, String> {
+    let mut attrs: BTreeMap<String, String> = BTreeMap::new();
+    let Some(elements) = extensions.get("torznab").and_then(|e| e.get("attr")) else {
+        return attrs;
+    };
+    for element in elements {
+        let (Some(name), Some(value)) = (element.attrs.get("name"), element.attrs.get("value")) else {
+            continue;
+        };
+        attrs
+            .entry(name.clone())
+            .and_modify(|v| {
+                v.push(',');
+                v.push_str(value);
+            })
+            .or_insert_with(|| value.clone());
+    }
+    attrs
+}
+
+fn error_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"<error\s+code="(\d+)"\s+description="([^"]*)""#).unwrap())
+}
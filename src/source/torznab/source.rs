@@ -0,0 +1,105 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use reqwest::Client;
+use reqwest::Url;
+
+use super::Torznab;
+use crate::source::build_client;
+use crate::source::Feed;
+use crate::source::Feeds;
+use crate::source::Item;
+use crate::source::Source;
+use crate::source::SourcePtr;
+use crate::Result;
+use crate::RetryPolicy;
+use crate::Schedule;
+use crate::TorznabConfig;
+use crate::TorznabMode;
+
+pub struct TorznabSource {
+    feeds: Feeds,
+    client: Client,
+    schedule: Schedule,
+    torrent: bool,
+}
+
+impl TorznabSource {
+    pub fn try_create(config: &TorznabConfig) -> Result<SourcePtr> {
+        let base = format!("{}/api", config.url.trim_end_matches('/'));
+
+        let mut params = vec![
+            ("apikey", config.apikey.expose().to_string()),
+            ("t", config.mode.to_string()),
+            ("q", config.query.clone()),
+        ];
+        if !config.categories.is_empty() {
+            let categories = config
+                .categories
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>();
+            params.push(("cat", categories.join(",")));
+        }
+        if let (TorznabMode::TvSearch, Some(season)) = (config.mode, config.season) {
+            params.push(("season", season.to_string()));
+        }
+
+        Ok(Arc::new(Self {
+            feeds: Feeds::new(
+                "Torznab",
+                vec![Feed::new(Url::parse_with_params(&base, &params)?)],
+                RetryPolicy::new(&config.retry),
+            ),
+            client: build_client(&config.http)?,
+            schedule: Schedule::new(config.interval, &config.schedule)?,
+            torrent: config.torrent,
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl Source for TorznabSource {
+    fn name(&self) -> String {
+        "Torznab".to_string()
+    }
+
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    async fn pull_items(&self) -> Result<Vec<Item>> {
+        self.feeds
+            .pull(&self.client, |content| Torznab::parse_items(content))
+            .await
+    }
+
+    fn client(&self) -> &Client {
+        &self.client
+    }
+
+    fn rsses(&self) -> Vec<String> {
+        self.feeds.urls()
+    }
+
+    fn feeds(&self) -> &Feeds {
+        &self.feeds
+    }
+
+    fn parse_torrent(&self) -> bool {
+        self.torrent
+    }
+}
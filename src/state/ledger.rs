@@ -0,0 +1,264 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt;
+
+use chrono::DateTime;
+use chrono::Local;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::release::normalize_name;
+use crate::release::ReleaseInfo;
+use crate::SeriesConfig;
+
+/// Records which episodes of each series have been seen.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EpisodeLedger {
+    pub series: Vec<SeriesRecord>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SeriesRecord {
+    /// The name to display.
+    pub title: String,
+    /// Normalized names used to match releases. See [`ReleaseInfo::series_names`].
+    pub names: Vec<String>,
+    pub season: Option<u32>,
+    /// Episodes seen and when they were first seen.
+    pub episodes: BTreeMap<u32, DateTime<Local>>,
+    /// Alerts already sent, by notifier.
+    pub alerts: BTreeMap<String, AlertMark>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertMark {
+    /// Missing episodes already reported.
+    pub missing: BTreeSet<u32>,
+    /// If the season-complete alert is sent.
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EpisodeEvent {
+    /// Episodes between the first and the latest seen are missing.
+    Missing {
+        series: String,
+        episodes: Vec<u32>,
+        latest: u32,
+    },
+    /// The latest episode reached the expected number of episodes and none is missing.
+    Complete { series: String, episodes: u32 },
+}
+
+impl fmt::Display for EpisodeEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpisodeEvent::Missing {
+                series,
+                episodes,
+                latest,
+            } => write!(
+                f,
+                "{}: episode {} missing (latest: {})",
+                series,
+                format_episodes(episodes.iter().copied()),
+                latest
+            ),
+            EpisodeEvent::Complete { series, episodes } => {
+                write!(f, "{series}: season complete ({episodes} episodes)")
+            }
+        }
+    }
+}
+
+impl SeriesRecord {
+    pub fn display_name(&self) -> String {
+        match self.season {
+            Some(season) => format!("{} S{}", self.title, season),
+            None => self.title.clone(),
+        }
+    }
+
+    /// The latest episode seen.
+    pub fn latest(&self) -> Option<u32> {
+        self.episodes.keys().next_back().copied()
+    }
+
+    /// Episodes between the first and the latest seen that are not seen yet.
+    pub fn missing(&self) -> Vec<u32> {
+        match (self.episodes.keys().next(), self.latest()) {
+            (Some(first), Some(latest)) => (*first..latest)
+                .filter(|e| !self.episodes.contains_key(e))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// The expected number of episodes from the config.
+    pub fn expected(&self, series: &[SeriesConfig]) -> Option<u32> {
+        configured(series, &self.names, self.season).map(|s| s.episodes)
+    }
+
+    /// The alerts not yet sent to `notifier`. The season is only complete when the
+    /// latest episode reaches the expected number and no episode is missing.
+    fn alerts(&mut self, notifier: &str, expected: &[SeriesConfig]) -> Vec<EpisodeEvent> {
+        let mut events = vec![];
+        let series = self.display_name();
+        let missing = self.missing();
+        let expected = self.expected(expected);
+        let Some(latest) = self.latest() else {
+            return events;
+        };
+        let mark = self.alerts.entry(notifier.to_string()).or_default();
+
+        let new_missing = missing
+            .iter()
+            .copied()
+            .filter(|e| !mark.missing.contains(e))
+            .collect::<Vec<_>>();
+        if !new_missing.is_empty() {
+            mark.missing.extend(&new_missing);
+            events.push(EpisodeEvent::Missing {
+                series: series.clone(),
+                episodes: new_missing,
+                latest,
+            });
+        }
+        if let Some(expected) = expected {
+            if latest >= expected && missing.is_empty() && !mark.complete {
+                mark.complete = true;
+                events.push(EpisodeEvent::Complete {
+                    series,
+                    episodes: expected,
+                });
+            }
+        }
+        events
+    }
+
+    fn matches(&self, names: &[String], season: Option<u32>) -> bool {
+        self.season == season && names.iter().any(|n| self.names.contains(n))
+    }
+}
+
+impl EpisodeLedger {
+    /// Record the episodes of a batch of releases and return the alerts not yet sent
+    /// to `notifier`.
+    ///
+    /// Only the series in `expected` are recorded, the records of the series removed
+    /// from it are dropped.
+    ///
+    /// The whole batch is recorded before the alerts are computed, as feeds list the
+    /// latest first and an episode may only look missing until the rest of the batch.
+    pub fn observe<'a>(
+        &mut self,
+        notifier: &str,
+        releases: impl IntoIterator<Item = &'a ReleaseInfo>,
+        expected: &[SeriesConfig],
+    ) -> Vec<EpisodeEvent> {
+        self.series.retain(|s| s.expected(expected).is_some());
+        let mut observed = BTreeSet::new();
+        for release in releases {
+            if let Some(i) = self.record(release, expected) {
+                observed.insert(i);
+            }
+        }
+        observed
+            .into_iter()
+            .flat_map(|i| self.series[i].alerts(notifier, expected))
+            .collect()
+    }
+
+    /// Record the episode of a release of the configured series, returns the index of
+    /// its series.
+    fn record(&mut self, release: &ReleaseInfo, expected: &[SeriesConfig]) -> Option<usize> {
+        let names = release.series_names();
+        let Some(episode) = release.episode else {
+            return None;
+        };
+        configured(expected, &names, release.season)?;
+
+        let i = match self
+            .series
+            .iter()
+            .position(|s| s.matches(&names, release.season))
+        {
+            Some(i) => i,
+            None => {
+                self.series.push(SeriesRecord {
+                    title: release.titles[0].clone(),
+                    season: release.season,
+                    ..Default::default()
+                });
+                self.series.len() - 1
+            }
+        };
+        let record = &mut self.series[i];
+        for name in names {
+            if !record.names.contains(&name) {
+                record.names.push(name);
+            }
+        }
+        record.episodes.entry(episode).or_insert_with(Local::now);
+        Some(i)
+    }
+
+    /// Find the series whose name contains `name`.
+    pub fn search(&self, name: &str) -> Vec<&SeriesRecord> {
+        let name = normalize_name(name);
+        self.series
+            .iter()
+            .filter(|s| s.names.iter().any(|n| n.contains(&name)))
+            .collect()
+    }
+}
+
+/// The configured series of the names and the season.
+fn configured<'a>(
+    series: &'a [SeriesConfig],
+    names: &[String],
+    season: Option<u32>,
+) -> Option<&'a SeriesConfig> {
+    series.iter().find(|s| {
+        names.contains(&normalize_name(&s.name)) && (s.season.is_none() || s.season == season)
+    })
+}
+
+/// Format episodes as ranges. Eg. `1-3, 5`.
+pub fn format_episodes(episodes: impl IntoIterator<Item = u32>) -> String {
+    let mut ranges: Vec<(u32, u32)> = vec![];
+    for e in episodes {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == e => *end = e,
+            _ => ranges.push((e, e)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{start}-{end}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,106 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod ledger;
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use chrono::DateTime;
+use chrono::Local;
+pub use ledger::format_episodes;
+pub use ledger::AlertMark;
+pub use ledger::EpisodeEvent;
+pub use ledger::EpisodeLedger;
+pub use ledger::SeriesRecord;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::HeldEpisode;
+use crate::Result;
+
+/// The state persisted between runs.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    /// Episodes seen of each series.
+    pub ledger: EpisodeLedger,
+    /// The publish time of the latest item notified by each task, keyed by
+    /// `<source>/<notifier>`. Older items are not notified again.
+    pub cursors: BTreeMap<String, DateTime<Local>>,
+    /// The publish time and title of the items notified after the cursor of each
+    /// task. The cursor is held back while some feeds of the source fail, these are
+    /// not notified again meanwhile.
+    pub notified: BTreeMap<String, BTreeSet<(DateTime<Local>, String)>>,
+    /// Releases held by the collapser of each task, keyed like `cursors`. They are
+    /// already behind the cursor, so they are only notified from here.
+    pub held: BTreeMap<String, Vec<HeldEpisode>>,
+}
+
+/// Shared state with an optional file to persist it.
+pub struct StateStore {
+    path: Option<PathBuf>,
+    state: Mutex<State>,
+}
+
+pub type StatePtr = Arc<StateStore>;
+
+impl StateStore {
+    /// Load the state from `path`. A missing file means an empty state.
+    ///
+    /// If `path` is `None`, the state is kept in memory only.
+    pub fn open(path: Option<&str>) -> Result<StatePtr> {
+        let path = path.map(PathBuf::from);
+        let state = match &path {
+            Some(path) if path.exists() => serde_json::from_slice(&fs::read(path)?)?,
+            _ => State::default(),
+        };
+        Ok(Arc::new(Self {
+            path,
+            state: Mutex::new(state),
+        }))
+    }
+
+    /// Load the state from `path` without persisting the changes, for `--dry-run`.
+    pub fn snapshot(path: Option<&str>) -> Result<StatePtr> {
+        let store = Self::open(path)?;
+        let state = store.read(|state| state.clone());
+        Ok(Arc::new(Self {
+            path: None,
+            state: Mutex::new(state),
+        }))
+    }
+
+    /// Read the state.
+    pub fn read<R>(&self, f: impl FnOnce(&State) -> R) -> R {
+        f(&self.state.lock().unwrap())
+    }
+
+    /// Modify the state and persist it.
+    pub fn update<R>(&self, f: impl FnOnce(&mut State) -> R) -> Result<R> {
+        let mut state = self.state.lock().unwrap();
+        let r = f(&mut state);
+        if let Some(path) = &self.path {
+            // Write to a temporary file first so that a crash will not corrupt the state.
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, serde_json::to_vec_pretty(&*state)?)?;
+            fs::rename(tmp, path)?;
+        }
+        Ok(r)
+    }
+}
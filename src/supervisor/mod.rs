@@ -0,0 +1,188 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Restart the polling tasks when they crash.
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use backon::BackoffBuilder;
+use chrono::DateTime;
+use chrono::Local;
+use log::error;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::metrics::metrics;
+use crate::BackoffKind;
+use crate::RetryConfig;
+use crate::RetryPolicy;
+
+/// The delay before the first restart, doubled after each crash.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+/// The max delay before a restart. The delay is reset if the task runs longer than it.
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TaskHealth {
+    /// False while waiting to restart after a crash.
+    pub running: bool,
+    pub restarts: u64,
+    /// The panic message of the last crash.
+    pub last_error: Option<String>,
+    pub last_crash: Option<DateTime<Local>>,
+}
+
+/// Runs tasks until they are cancelled, restarting them with backoff when they
+/// panic or exit unexpectedly.
+#[derive(Clone)]
+pub struct Supervisor {
+    policy: RetryPolicy,
+    health: Arc<Mutex<BTreeMap<String, TaskHealth>>>,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::new(RetryPolicy::new(&RetryConfig {
+            backoff: BackoffKind::Exponential,
+            max_times: usize::MAX,
+            delay: RESTART_DELAY,
+            max_delay: MAX_RESTART_DELAY,
+            jitter: true,
+        }))
+    }
+}
+
+impl Supervisor {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            health: Arc::default(),
+        }
+    }
+
+    /// Run the task created by `start` until `token` is cancelled.
+    ///
+    /// Aborting the returned handle aborts the task too.
+    pub fn spawn<F, Fut>(
+        &self,
+        name: String,
+        token: CancellationToken,
+        mut start: F,
+    ) -> JoinHandle<()>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let supervisor = self.clone();
+        supervisor.update(&name, |health| {
+            *health = TaskHealth {
+                running: true,
+                ..Default::default()
+            }
+        });
+        tokio::spawn(async move {
+            let mut backoff = supervisor.policy.build();
+            loop {
+                let started = Instant::now();
+                let mut task = AbortOnDrop(tokio::spawn(start()));
+                let result = (&mut task.0).await;
+                if token.is_cancelled() {
+                    return;
+                }
+
+                let reason = match result {
+                    Ok(()) => "exited unexpectedly".to_string(),
+                    Err(e) if e.is_panic() => {
+                        format!("panicked: {}", panic_message(e.into_panic()))
+                    }
+                    Err(e) => e.to_string(),
+                };
+                if started.elapsed() >= MAX_RESTART_DELAY {
+                    backoff = supervisor.policy.build();
+                }
+                let delay = backoff.next().unwrap_or(MAX_RESTART_DELAY);
+                error!(
+                    task:% = name, restart_after_ms = delay.as_millis() as u64;
+                    "Task '{}' {}, restart after {:?}", name, reason, delay
+                );
+                supervisor.update(&name, |health| {
+                    health.running = false;
+                    health.restarts += 1;
+                    health.last_error = Some(reason);
+                    health.last_crash = Some(Local::now());
+                });
+                metrics().inc_counter(
+                    "blooming_task_restarts_total",
+                    "The number of restarts of the task after crashes.",
+                    &[("task", &name)],
+                    1.0,
+                );
+
+                tokio::select! {
+                    _ = token.cancelled() => return,
+                    _ = tokio::time::sleep(delay) => {}
+                }
+                supervisor.update(&name, |health| health.running = true);
+            }
+        })
+    }
+
+    /// The health of each task, by name.
+    pub fn health(&self) -> BTreeMap<String, TaskHealth> {
+        self.health.lock().unwrap().clone()
+    }
+
+    /// Forget a task stopped on purpose.
+    pub fn remove(&self, name: &str) {
+        self.health.lock().unwrap().remove(name);
+        metrics().remove("blooming_task_up", &[("task", name)]);
+    }
+
+    fn update(&self, name: &str, f: impl FnOnce(&mut TaskHealth)) {
+        let mut health = self.health.lock().unwrap();
+        let health = health.entry(name.to_string()).or_default();
+        f(health);
+        metrics().set_gauge(
+            "blooming_task_up",
+            "Whether the task is running, 0 while waiting to restart after a crash.",
+            &[("task", name)],
+            if health.running { 1.0 } else { 0.0 },
+        );
+    }
+}
+
+/// Aborts the task when dropped, so that aborting the supervisor aborts the task too.
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
@@ -0,0 +1,235 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+
+use crate::Result;
+
+/// The deepest nesting of lists and dictionaries accepted. Torrents from the network
+/// could otherwise overflow the stack of the recursive decoder.
+const MAX_DEPTH: usize = 64;
+
+/// A decoded bencode value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Dict(BTreeMap<Vec<u8>, Value>),
+}
+
+impl Value {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// The value as an UTF-8 string. Invalid sequences are replaced.
+    pub fn as_str(&self) -> Option<String> {
+        self.as_bytes()
+            .map(|b| String::from_utf8_lossy(b).into_owned())
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&BTreeMap<Vec<u8>, Value>> {
+        match self {
+            Value::Dict(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Get the value of `key` if this is a dictionary.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_dict().and_then(|d| d.get(key.as_bytes()))
+    }
+}
+
+/// Decode a complete bencode document.
+pub fn decode(data: &[u8]) -> Result<Value> {
+    let mut decoder = Decoder::new(data);
+    let value = decoder.value()?;
+    decoder.finish()?;
+    Ok(value)
+}
+
+/// Decode a bencode dictionary and also return the raw bytes of the value under `key`.
+///
+/// Info hashes must be computed over the exact bytes in the file, so re-encoding
+/// the decoded value is not good enough for non-canonical torrents.
+pub fn decode_with_raw<'a>(data: &'a [u8], key: &str) -> Result<(Value, Option<&'a [u8]>)> {
+    let mut decoder = Decoder::new(data);
+    decoder.expect(b'd')?;
+    decoder.depth = 1;
+
+    let mut dict = BTreeMap::new();
+    let mut raw = None;
+    while decoder.peek()? != b'e' {
+        let k = decoder.bytes()?;
+        let start = decoder.pos;
+        let v = decoder.value()?;
+        if k == key.as_bytes() {
+            raw = Some(&data[start..decoder.pos]);
+        }
+        dict.insert(k, v);
+    }
+    decoder.expect(b'e')?;
+    decoder.finish()?;
+
+    Ok((Value::Dict(dict), raw))
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Lists and dictionaries the decoder is in.
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn peek(&self) -> Result<u8> {
+        self.data
+            .get(self.pos)
+            .copied()
+            .ok_or_else(|| anyhow!("Unexpected end of bencode data"))
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        let got = self.peek()?;
+        if got != c {
+            return Err(anyhow!(
+                "Expect '{}' at {} of bencode data, got '{}'",
+                c as char,
+                self.pos,
+                got as char
+            ));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<()> {
+        if self.pos != self.data.len() {
+            return Err(anyhow!("Trailing bytes after bencode data at {}", self.pos));
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        match self.peek()? {
+            b'i' => self.int().map(Value::Int),
+            b'l' => {
+                self.enter()?;
+                let mut list = Vec::new();
+                while self.peek()? != b'e' {
+                    list.push(self.value()?);
+                }
+                self.leave();
+                Ok(Value::List(list))
+            }
+            b'd' => {
+                self.enter()?;
+                let mut dict = BTreeMap::new();
+                while self.peek()? != b'e' {
+                    let k = self.bytes()?;
+                    let v = self.value()?;
+                    dict.insert(k, v);
+                }
+                self.leave();
+                Ok(Value::Dict(dict))
+            }
+            b'0'..=b'9' => self.bytes().map(Value::Bytes),
+            c => Err(anyhow!(
+                "Invalid bencode token '{}' at {}",
+                c as char,
+                self.pos
+            )),
+        }
+    }
+
+    /// Step into a list or a dictionary.
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= MAX_DEPTH {
+            return Err(anyhow!(
+                "Bencode data at {} is nested deeper than {}",
+                self.pos,
+                MAX_DEPTH
+            ));
+        }
+        self.depth += 1;
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Step over the `e` that ends a list or a dictionary.
+    fn leave(&mut self) {
+        self.depth -= 1;
+        self.pos += 1;
+    }
+
+    fn int(&mut self) -> Result<i64> {
+        self.expect(b'i')?;
+        let digits = self.until(b'e')?;
+        self.pos += 1;
+        Ok(digits.parse()?)
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>> {
+        let len: usize = self.until(b':')?.parse()?;
+        self.pos += 1;
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| anyhow!("Bencode string at {} is out of range", self.pos))?;
+        let bytes = self.data[self.pos..end].to_vec();
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Read ASCII text until `delim` (exclusive).
+    fn until(&mut self, delim: u8) -> Result<&'a str> {
+        let start = self.pos;
+        let len = self.data[start..]
+            .iter()
+            .position(|c| *c == delim)
+            .ok_or_else(|| anyhow!("Unexpected end of bencode data"))?;
+        self.pos += len;
+        Ok(std::str::from_utf8(&self.data[start..self.pos])?)
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use percent_encoding::utf8_percent_encode;
+use percent_encoding::AsciiSet;
+use percent_encoding::NON_ALPHANUMERIC;
+use serde::Deserialize;
+use serde::Serialize;
+use sha1::Digest;
+use sha1::Sha1;
+use sha2::Sha256;
+
+use super::bencode;
+use super::Value;
+use crate::Result;
+
+const MAGNET_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Metadata extracted from a `.torrent` file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TorrentInfo {
+    /// BitTorrent v1 info hash (SHA-1, hex).
+    pub info_hash_v1: Option<String>,
+    /// BitTorrent v2 info hash (SHA-256, hex).
+    pub info_hash_v2: Option<String>,
+    /// Suggested name of the file or the root directory.
+    pub name: String,
+    /// Files in the torrent, padding files excluded.
+    pub files: Vec<TorrentFile>,
+    /// Size of each piece. (bytes)
+    pub piece_length: u64,
+    /// Sum of the file sizes. (bytes)
+    pub total_size: u64,
+    /// Tracker URLs from `announce` and `announce-list`, deduplicated.
+    pub trackers: Vec<String>,
+    /// If the torrent is from a private tracker.
+    ///
+    /// Trackers of private torrents usually contain the passkey of the user,
+    /// so they are never put into magnet links.
+    pub private: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TorrentFile {
+    /// Path relative to the root directory, joined by '/'.
+    pub path: String,
+    /// Size of the file. (bytes)
+    pub length: u64,
+}
+
+impl TorrentInfo {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let (root, raw_info) = bencode::decode_with_raw(data, "info")?;
+        let raw_info = raw_info.ok_or_else(|| anyhow!("Torrent has no info dictionary"))?;
+        let info = root
+            .get("info")
+            .filter(|v| v.as_dict().is_some())
+            .ok_or_else(|| anyhow!("Torrent info is not a dictionary"))?;
+
+        // v1 (and hybrid) torrents carry `pieces`; v2 (and hybrid) torrents set `meta version` to 2.
+        let info_hash_v1 = info.get("pieces").map(|_| to_hex(&Sha1::digest(raw_info)));
+        let info_hash_v2 = (info.get("meta version").and_then(Value::as_int) == Some(2))
+            .then(|| to_hex(&Sha256::digest(raw_info)));
+
+        let name = info
+            .get("name.utf-8")
+            .or_else(|| info.get("name"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let piece_length = info
+            .get("piece length")
+            .and_then(Value::as_int)
+            .ok_or_else(|| anyhow!("Torrent info has no piece length"))?;
+
+        let files = if let Some(files) = info.get("files").and_then(Value::as_list) {
+            v1_files(files)?
+        } else if let Some(length) = info.get("length").and_then(Value::as_int) {
+            vec![TorrentFile {
+                path: name.clone(),
+                length: to_length(length)?,
+            }]
+        } else if let Some(tree) = info.get("file tree").and_then(Value::as_dict) {
+            let mut files = Vec::new();
+            v2_files(tree, "", &mut files)?;
+            files
+        } else {
+            return Err(anyhow!("Torrent info has no file list"));
+        };
+        let total_size = files
+            .iter()
+            .try_fold(0u64, |total, f| total.checked_add(f.length))
+            .ok_or_else(|| anyhow!("Total size of the torrent is out of range"))?;
+
+        let private = info.get("private").and_then(Value::as_int) == Some(1);
+
+        let mut trackers: Vec<String> = Vec::new();
+        let announce = root.get("announce").and_then(Value::as_str);
+        let announce_list = root
+            .get("announce-list")
+            .and_then(Value::as_list)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(Value::as_list)
+            .flatten()
+            .filter_map(Value::as_str);
+        for tracker in announce.into_iter().chain(announce_list) {
+            if !tracker.is_empty() && !trackers.contains(&tracker) {
+                trackers.push(tracker);
+            }
+        }
+
+        Ok(Self {
+            info_hash_v1,
+            info_hash_v2,
+            name,
+            files,
+            piece_length: to_length(piece_length)?,
+            total_size,
+            trackers,
+            private,
+        })
+    }
+
+    /// Build a magnet link with the display name and the trackers.
+    ///
+    /// Trackers are left out for private torrents.
+    pub fn magnet(&self) -> Option<String> {
+        let mut params = Vec::new();
+        if let Some(hash) = &self.info_hash_v1 {
+            params.push(format!("xt=urn:btih:{hash}"));
+        }
+        if let Some(hash) = &self.info_hash_v2 {
+            // Multihash of SHA-256: 0x12 and 32 bytes length.
+            params.push(format!("xt=urn:btmh:1220{hash}"));
+        }
+        if params.is_empty() {
+            return None;
+        }
+        if !self.name.is_empty() {
+            params.push(format!("dn={}", encode(&self.name)));
+        }
+        if !self.private {
+            params.extend(self.trackers.iter().map(|t| format!("tr={}", encode(t))));
+        }
+        Some(format!("magnet:?{}", params.join("&")))
+    }
+}
+
+fn encode(s: &str) -> String {
+    utf8_percent_encode(s, MAGNET_ENCODE_SET).to_string()
+}
+
+/// Lengths are integers in bencode, negative ones are rejected instead of wrapping.
+fn to_length(length: i64) -> Result<u64> {
+    u64::try_from(length).map_err(|_| anyhow!("Negative length {} in torrent", length))
+}
+
+fn v1_files(files: &[Value]) -> Result<Vec<TorrentFile>> {
+    files
+        .iter()
+        .filter(|f| {
+            // BEP 47 padding files.
+            !f.get("attr")
+                .and_then(Value::as_str)
+                .map_or(false, |attr| attr.contains('p'))
+        })
+        .filter_map(|f| {
+            let length = f.get("length")?.as_int()?;
+            let path = f
+                .get("path.utf-8")
+                .or_else(|| f.get("path"))?
+                .as_list()?
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("/");
+            Some((path, length))
+        })
+        .map(|(path, length)| {
+            Ok(TorrentFile {
+                path,
+                length: to_length(length)?,
+            })
+        })
+        .collect()
+}
+
+fn v2_files(
+    tree: &BTreeMap<Vec<u8>, Value>,
+    prefix: &str,
+    files: &mut Vec<TorrentFile>,
+) -> Result<()> {
+    for (k, v) in tree {
+        // A file node is a dictionary with a single empty key.
+        if k.is_empty() {
+            if let Some(length) = v.get("length").and_then(Value::as_int) {
+                files.push(TorrentFile {
+                    path: prefix.to_string(),
+                    length: to_length(length)?,
+                });
+            }
+            continue;
+        }
+        let name = String::from_utf8_lossy(k);
+        let path = if prefix.is_empty() {
+            name.into_owned()
+        } else {
+            format!("{prefix}/{name}")
+        };
+        if let Some(subtree) = v.as_dict() {
+            v2_files(subtree, &path, files)?;
+        }
+    }
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
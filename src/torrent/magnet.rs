@@ -0,0 +1,64 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Extract the v1 info hash (hex) from a magnet link.
+///
+/// Both the hex and the base32 forms of `xt=urn:btih:` are supported.
+pub fn info_hash_from_magnet(magnet: &str) -> Option<String> {
+    let query = magnet.strip_prefix("magnet:?")?;
+    let hash = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("xt=urn:btih:"))?;
+    match hash.len() {
+        40 if hash.chars().all(|c| c.is_ascii_hexdigit()) => Some(hash.to_lowercase()),
+        32 => base32_decode(hash).map(|bytes| bytes.iter().map(|b| format!("{b:02x}")).collect()),
+        _ => None,
+    }
+}
+
+/// Remove the trackers (`tr=`) from a magnet link.
+///
+/// Trackers of private sites carry the passkey, eg. the magnet links of private
+/// indexers of Jackett and Prowlarr, so the magnet links from feeds must not be
+/// sent as they are.
+pub fn strip_trackers(magnet: &str) -> String {
+    let Some(query) = magnet.strip_prefix("magnet:?") else {
+        return magnet.to_string();
+    };
+    let params = query
+        .split('&')
+        .filter(|param| !param.starts_with("tr=") && !param.starts_with("tr."))
+        .collect::<Vec<_>>();
+    format!("magnet:?{}", params.join("&"))
+}
+
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer = 0u64;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u64;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
@@ -0,0 +1,42 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod bencode;
+mod info;
+mod magnet;
+
+pub use bencode::decode;
+pub use bencode::Value;
+pub use info::TorrentFile;
+pub use info::TorrentInfo;
+pub use magnet::info_hash_from_magnet;
+pub use magnet::strip_trackers;
+use reqwest::Client;
+
+use crate::Result;
+
+/// Download a `.torrent` file and parse it.
+pub async fn fetch(client: &Client, url: &str) -> Result<TorrentInfo> {
+    // Urls of PT sites contain passkeys, so they are dropped from the errors.
+    let content = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(reqwest::Error::without_url)?
+        .bytes()
+        .await
+        .map_err(reqwest::Error::without_url)?;
+    TorrentInfo::parse(&content)
+}
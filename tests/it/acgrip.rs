@@ -0,0 +1,104 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::File;
+use std::io::BufReader;
+
+use blooming::register;
+use blooming::AcgRip;
+use blooming::AcgRipConfig;
+use blooming::Config;
+use blooming::Result;
+use blooming::SourceFactory;
+
+use crate::notifier::serve;
+
+#[test]
+fn test_parse_acgrip() -> Result<()> {
+    let file = File::open("tests/it/testdata/acgrip.xml")?;
+    let items = AcgRip::parse_items(BufReader::new(file))?;
+    assert_eq!(items.len(), 2);
+
+    let item = &items[0];
+    assert_eq!(item.url, "https://acg.rip/t/273561");
+    assert_eq!(
+        item.enclosure.as_deref(),
+        Some("https://acg.rip/t/273561.torrent")
+    );
+    assert_eq!(item.torrent_url(), "https://acg.rip/t/273561.torrent");
+    assert_eq!(item.team.as_deref(), Some("豌豆字幕组"));
+    assert_eq!(item.magnet(), None);
+
+    let item = &items[1];
+    assert_eq!(item.team.as_deref(), Some("Nekomoe kissaten"));
+    assert_eq!(
+        item.release.as_ref().unwrap().subtitle.as_deref(),
+        Some("JPSC")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_acgrip_rsses() -> Result<()> {
+    let mut config = Config {
+        acgrip: Some(AcgRipConfig {
+            base_url: "http://localhost:8080/acgrip/".to_string(),
+            keywords: vec!["海盗战记".to_string()],
+            category: Some(1),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+    assert_eq!(factory.sources()[0].rsses(), vec![
+        "http://localhost:8080/acgrip/1.xml?term=%E6%B5%B7%E7%9B%97%E6%88%98%E8%AE%B0",
+    ]);
+
+    // The team takes precedence over the category.
+    let acgrip = config.acgrip.as_mut().unwrap();
+    acgrip.keywords.clear();
+    acgrip.team_id = Some(185);
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+    assert_eq!(factory.sources()[0].rsses(), vec![
+        "http://localhost:8080/acgrip/team/185.xml",
+    ]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_acgrip_dedup() -> Result<()> {
+    let feed = std::fs::read_to_string("tests/it/testdata/acgrip.xml")?;
+    let (addr, requests) = serve(move |_, _| ("200 OK", feed.clone())).await?;
+    let config = Config {
+        acgrip: Some(AcgRipConfig {
+            base_url: format!("http://{addr}/"),
+            keywords: vec!["海盗战记".to_string(), "vinland".to_string()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+
+    // A release matching both keywords is only returned once.
+    let items = factory.sources()[0].pull_items().await?;
+    assert_eq!(requests.lock().unwrap().len(), 2);
+    assert_eq!(items.len(), 2);
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::SocketAddr;
+
+use blooming::check::check_config;
+use blooming::check::check_live;
+use blooming::check::notify_test;
+use blooming::check::Status;
+use blooming::Config;
+use blooming::Result;
+
+/// Serve the BYRBT fixture at `/ok`, the login info and sending messages of go-cqhttp,
+/// and 404 otherwise. Sending group messages always fails.
+async fn serve() -> Result<SocketAddr> {
+    let feed = std::fs::read_to_string("tests/it/testdata/byrbt.xml")?;
+    let (addr, _) = crate::notifier::serve(move |line, _| {
+        if line.starts_with("GET /ok ") {
+            ("200 OK", feed.clone())
+        } else if line.starts_with("GET /get_login_info ") {
+            let info = r#"{"retcode":0,"data":{"user_id":10001,"nickname":"BOT"}}"#;
+            ("200 OK", info.to_string())
+        } else if line.starts_with("POST /send_private_forward_msg ") {
+            ("200 OK", r#"{"status":"ok","retcode":0}"#.to_string())
+        } else if line.starts_with("POST /send_group_forward_msg ") {
+            let resp = r#"{"status":"failed","retcode":100,"msg":"GROUP_NOT_FOUND","wording":"group not found"}"#;
+            ("200 OK", resp.to_string())
+        } else {
+            ("404 Not Found", String::new())
+        }
+    })
+    .await?;
+    Ok(addr)
+}
+
+fn statuses(report: &blooming::check::Report) -> Vec<(&str, Status)> {
+    report
+        .entries
+        .iter()
+        .map(|e| (e.component.as_str(), e.status))
+        .collect()
+}
+
+#[test]
+fn test_check_config() -> Result<()> {
+    let config = Config::parse(
+        r#"
+        [defaults]
+        interval = "5m"
+
+        [byrbt]
+        rsses = ["https://byr.pt/torrentrss.php", "ftp://byr.pt/rss"]
+
+        [tjupt]
+        rsses = []
+
+        [nyaa.schedule]
+        cron = "every minute"
+
+        [qq]
+        api = "http://localhost:5700"
+        groups = [1]
+
+        [qq_guild]
+        app_id = "1"
+        "#,
+    )?;
+    let report = check_config(&config);
+
+    assert_eq!(statuses(&report), vec![
+        ("source byrbt", Status::Fail),
+        ("source tjupt", Status::Warn),
+        ("source nyaa", Status::Fail),
+        ("notifier qq", Status::Ok),
+        ("notifier qq_guild", Status::Fail),
+    ]);
+    assert!(
+        report.entries[0]
+            .message
+            .contains("unsupported scheme 'ftp'")
+    );
+    assert!(
+        report.entries[2]
+            .message
+            .contains("Invalid cron expression")
+    );
+    assert_eq!(
+        report.entries[4].message,
+        "`qq_guild.app_secret`, `qq_guild.channel_id` not set"
+    );
+    assert_eq!(report.exit_code(), 1);
+
+    let config = Config::parse("[nyaa]\ninterval = 60")?;
+    let report = check_config(&config);
+    assert_eq!(statuses(&report), vec![
+        ("source nyaa", Status::Ok),
+        ("notifier", Status::Warn),
+    ]);
+    assert_eq!(report.exit_code(), 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_live() -> Result<()> {
+    let addr = serve().await?;
+    let config = Config::parse(&format!(
+        r#"
+        [byrbt]
+        interval = 60
+        rsses = ["http://{addr}/ok", "http://{addr}/missing"]
+
+        [qq]
+        uin = "10001"
+        api = "http://{addr}"
+        "#
+    ))?;
+    let report = check_live(&config).await;
+
+    let ok = format!("source byrbt feed 'http://{addr}/ok'");
+    let missing = format!("source byrbt feed 'http://{addr}/missing'");
+    assert_eq!(statuses(&report), vec![
+        (ok.as_str(), Status::Ok),
+        (missing.as_str(), Status::Fail),
+        ("notifier qq", Status::Ok),
+    ]);
+    assert!(report.entries[1].message.contains("404"));
+    assert_eq!(report.entries[2].message, "logged in as BOT (10001)");
+    assert_eq!(report.exit_code(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_notify_test() -> Result<()> {
+    let addr = serve().await?;
+    let config = Config::parse(&format!(
+        r#"
+        [qq]
+        api = "http://{addr}"
+        dms = [1]
+        groups = [2]
+        delay = 0
+        "#
+    ))?;
+
+    let report = notify_test(&config, None, false).await?;
+    assert_eq!(statuses(&report), vec![
+        ("notifier qq dm 1", Status::Ok),
+        ("notifier qq group 2", Status::Fail),
+    ]);
+    assert_eq!(report.entries[1].message, "retcode 100: group not found");
+    assert_eq!(report.exit_code(), 1);
+
+    let report = notify_test(&config, Some("qq"), true).await?;
+    assert_eq!(statuses(&report), vec![("notifier qq dry-run", Status::Ok)]);
+
+    let err = notify_test(&config, Some("qq_guild"), false)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Notifier 'qq_guild' is not configured, configured: [qq]"
+    );
+
+    Ok(())
+}
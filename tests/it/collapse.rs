@@ -0,0 +1,87 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use blooming::release::ReleaseInfo;
+use blooming::CollapseConfig;
+use blooming::Collapser;
+use blooming::Item;
+
+fn item(title: &str) -> Item {
+    Item {
+        title: title.to_string(),
+        release: Some(ReleaseInfo::parse(title)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_collapse() {
+    let conf = CollapseConfig {
+        window: Duration::from_secs(600),
+        groups: vec!["LoliHouse".to_string()],
+        resolutions: vec!["1080P".to_string(), "720P".to_string()],
+        subtitles: vec!["简体".to_string()],
+    };
+    let mut collapser = Collapser::new(conf);
+    let now = Instant::now();
+
+    let ready = collapser.collapse(
+        vec![
+            item("【豌豆字幕组】[海盗战记 / 冰海战记 第二季 / Vinland_Saga_S2][03][繁体][720P][MP4]"),
+            item("【豌豆字幕组】[海盗战记 / 冰海战记 第二季 / Vinland_Saga_S2][03][简体][1080P][MP4]"),
+            item("[ANi] The Vampire Dies in No Time S2 - 吸血鬼马上死 第二季 - 03"),
+            // Not a release title, passes through.
+            item("Some random title"),
+        ],
+        now,
+    );
+    assert_eq!(ready.len(), 1);
+    assert_eq!(ready[0].title, "Some random title");
+
+    let ready = collapser.collapse(
+        vec![
+            item("[LoliHouse] Vinland Saga S2 - 03 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]"),
+            // Another episode.
+            item("[LoliHouse] Vinland Saga S2 - 04 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]"),
+        ],
+        now + Duration::from_secs(300),
+    );
+    assert!(ready.is_empty());
+
+    // Only the episodes first seen at least 600 seconds ago are ready.
+    let mut ready = collapser.collapse(vec![], now + Duration::from_secs(600));
+    assert_eq!(ready.len(), 2);
+    ready.sort_by_key(|item| item.release.as_ref().unwrap().episode);
+
+    let vinland = &ready[0];
+    assert!(
+        vinland
+            .title
+            .starts_with("[LoliHouse] Vinland Saga S2 - 03")
+    );
+    assert_eq!(vinland.alternatives.len(), 2);
+    assert_eq!(
+        vinland.alternatives_brief().unwrap(),
+        "豌豆字幕组 1080P 简体 | 豌豆字幕组 720P 繁体"
+    );
+    assert!(ready[1].title.starts_with("[ANi]"));
+    assert!(ready[1].alternatives.is_empty());
+
+    let ready = collapser.collapse(vec![], now + Duration::from_secs(900));
+    assert_eq!(ready.len(), 1);
+    assert!(ready[0].title.contains("Vinland Saga S2 - 04"));
+}
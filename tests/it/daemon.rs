@@ -0,0 +1,99 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+
+use blooming::daemon::status;
+use blooming::daemon::DaemonStatus;
+use blooming::daemon::LogFile;
+use blooming::DaemonConfig;
+use blooming::LogRotation;
+use blooming::Result;
+use chrono::Duration;
+use chrono::Local;
+
+fn temp_dir(name: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("blooming-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+#[test]
+fn test_log_rotation() -> Result<()> {
+    let dir = temp_dir("log")?;
+    let path = dir.join("blooming.log");
+    let config = DaemonConfig {
+        log_file: path.to_str().unwrap().to_string(),
+        log_max_size: 10,
+        log_rotation: LogRotation::Daily,
+        log_keep: 2,
+        ..Default::default()
+    };
+
+    let (mut log, _) = LogFile::open(&config)?;
+    assert!(!log.should_rotate(Local::now()));
+    assert!(log.should_rotate(Local::now() + Duration::days(1)));
+
+    for content in ["first log\n", "second log\n", "third log\n"] {
+        fs::write(&path, content)?;
+        assert!(log.should_rotate(Local::now()));
+        log.rotate()?;
+    }
+    assert_eq!(fs::read_to_string(&path)?, "");
+    assert_eq!(
+        fs::read_to_string(dir.join("blooming.log.1"))?,
+        "third log\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join("blooming.log.2"))?,
+        "second log\n"
+    );
+    assert!(!dir.join("blooming.log.3").exists());
+
+    fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_status() -> Result<()> {
+    let dir = temp_dir("pid")?;
+    let pid_file = dir.join("blooming.pid");
+    let pid_file = pid_file.to_str().unwrap();
+
+    assert_eq!(status(pid_file)?, DaemonStatus::NotRunning);
+
+    fs::write(pid_file, "42\n")?;
+    assert_eq!(status(pid_file)?, DaemonStatus::Dead(42));
+    assert_eq!(status(pid_file)?.exit_code(), 1);
+
+    // The daemon locks its pid file.
+    let file = File::open(pid_file)?;
+    assert_eq!(
+        unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) },
+        0
+    );
+    assert_eq!(status(pid_file)?, DaemonStatus::Running(42));
+    assert_eq!(status(pid_file)?.to_string(), "running (pid 42)");
+    drop(file);
+
+    fs::write(pid_file, "not a pid")?;
+    assert!(status(pid_file).is_err());
+
+    fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,109 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::File;
+use std::io::BufReader;
+
+use blooming::register;
+use blooming::Config;
+use blooming::Dmhy;
+use blooming::DmhyConfig;
+use blooming::Result;
+use blooming::SourceFactory;
+
+use crate::notifier::serve;
+
+#[test]
+fn test_parse_dmhy() -> Result<()> {
+    let file = File::open("tests/it/testdata/dmhy.xml")?;
+    let items = Dmhy::parse_items(BufReader::new(file))?;
+    assert_eq!(items.len(), 2);
+
+    // Base32 info hash.
+    let item = &items[0];
+    assert_eq!(
+        item.url,
+        "http://share.dmhy.org/topics/view/629876_Vinland_Saga_S2_03_1080P_MP4.html"
+    );
+    assert_eq!(
+        item.info_hash.as_deref(),
+        Some("5dd79686d9b6c1ab2a6091363d493d05333d8899")
+    );
+    assert_eq!(item.team.as_deref(), Some("豌豆字幕组"));
+    assert_eq!(item.category.as_deref(), Some("動畫"));
+    assert_eq!(item.magnet(), item.magnet.clone());
+    assert!(
+        item.magnet()
+            .unwrap()
+            .starts_with("magnet:?xt=urn:btih:LXLZNBWZW3A2WKTASE3D2SJ5AUZT3CEZ")
+    );
+    let release = item.release.as_ref().unwrap();
+    assert_eq!(release.episode, Some(3));
+    assert_eq!(release.season, Some(2));
+
+    // Hex info hash.
+    let item = &items[1];
+    assert_eq!(
+        item.info_hash.as_deref(),
+        Some("fa2fca2b18dc4d6e166cab56fd36dcb547eafe6e")
+    );
+    assert_eq!(item.team.as_deref(), Some("ANi"));
+
+    Ok(())
+}
+
+#[test]
+fn test_dmhy_rsses() -> Result<()> {
+    let config = Config {
+        dmhy: Some(DmhyConfig {
+            base_url: "http://localhost:8080/dmhy/".to_string(),
+            keywords: vec!["海盗战记".to_string(), "vinland".to_string()],
+            sort_id: Some(2),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+
+    assert_eq!(factory.sources()[0].rsses(), vec![
+        "http://localhost:8080/dmhy/topics/rss/rss.xml?keyword=%E6%B5%B7%E7%9B%97%E6%88%98%E8%AE%B0&sort_id=2",
+        "http://localhost:8080/dmhy/topics/rss/rss.xml?keyword=vinland&sort_id=2",
+    ]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dmhy_dedup() -> Result<()> {
+    let feed = std::fs::read_to_string("tests/it/testdata/dmhy.xml")?;
+    let (addr, requests) = serve(move |_, _| ("200 OK", feed.clone())).await?;
+    let config = Config {
+        dmhy: Some(DmhyConfig {
+            base_url: format!("http://{addr}/"),
+            keywords: vec!["海盗战记".to_string(), "vinland".to_string()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+
+    // A release matching both keywords is only returned once.
+    let items = factory.sources()[0].pull_items().await?;
+    assert_eq!(requests.lock().unwrap().len(), 2);
+    assert_eq!(items.len(), 2);
+
+    Ok(())
+}
@@ -0,0 +1,320 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::SocketAddr;
+
+use blooming::metrics::metrics;
+use blooming::redact_url;
+use blooming::register;
+use blooming::ByrbtConfig;
+use blooming::Config;
+use blooming::Result;
+use blooming::RetryConfig;
+use blooming::SourceFactory;
+use chrono::Duration;
+use chrono::Local;
+use tokio::net::TcpListener;
+
+use crate::notifier::serve_http;
+use crate::notifier::Requests;
+
+/// Serve the BYRBT fixture and record the requests.
+///
+/// - `/ok`: 200 with `ETag`, 304 if `If-None-Match` matches.
+/// - `/limited`: 429 with `Retry-After`.
+/// - `/busy`: 429 without `Retry-After`.
+/// - `/flooded`: 503 with a huge `Retry-After`.
+/// - `/missing`: 404.
+/// - `/invalid`: 200 with a body that is not RSS.
+/// - others: 500.
+async fn serve() -> Result<(SocketAddr, Requests)> {
+    let feed = std::fs::read_to_string("tests/it/testdata/byrbt.xml")?;
+    serve_http(move |head, _| {
+        let (status, headers, body) = if head.starts_with("GET /ok ") {
+            if head.lines().any(|l| l == "if-none-match: \"v1\"") {
+                ("304 Not Modified", "ETag: \"v1\"\r\n", "")
+            } else {
+                (
+                    "200 OK",
+                    "ETag: \"v1\"\r\nLast-Modified: Tue, 24 Jan 2023 13:29:39 GMT\r\n",
+                    feed.as_str(),
+                )
+            }
+        } else if head.starts_with("GET /missing ") {
+            ("404 Not Found", "", "")
+        } else if head.starts_with("GET /invalid ") {
+            ("200 OK", "", "<html></html>")
+        } else if head.starts_with("GET /flooded ") {
+            (
+                "503 Service Unavailable",
+                "Retry-After: 9999999999999999\r\n",
+                "",
+            )
+        } else if head.starts_with("GET /busy ") {
+            ("429 Too Many Requests", "", "")
+        } else if head.starts_with("GET /limited ") {
+            ("429 Too Many Requests", "Retry-After: 120\r\n", "")
+        } else {
+            ("500 Internal Server Error", "", "")
+        };
+        (status, headers.to_string(), body.to_string())
+    })
+    .await
+}
+
+fn fast_retry() -> RetryConfig {
+    RetryConfig {
+        delay: std::time::Duration::from_millis(10),
+        max_times: 2,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_partial_feeds() -> Result<()> {
+    let (addr, _) = serve().await?;
+    let config = Config {
+        byrbt: Some(ByrbtConfig {
+            rsses: vec![
+                format!("http://{addr}/ok").into(),
+                format!("http://{addr}/bad").into(),
+            ],
+            retry: fast_retry(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+    let source = &factory.sources()[0];
+
+    // Items of the good feed are kept.
+    let items = source.pull_items().await?;
+    assert_eq!(items.len(), 2);
+
+    let status = source.feeds().status();
+    assert_eq!(status.len(), 2);
+    assert!(status[0].last_success.is_some());
+    assert_eq!(status[0].failures, 0);
+    assert_eq!(status[0].items, 2);
+    assert!(status[1].last_success.is_none());
+    assert_eq!(status[1].failures, 1);
+    assert!(status[1].last_error.as_ref().unwrap().contains("500"));
+    // urls may contain passkeys
+    assert!(!status[1].last_error.as_ref().unwrap().contains("/bad"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_all_feeds_failed() -> Result<()> {
+    let (addr, requests) = serve().await?;
+    let config = Config {
+        byrbt: Some(ByrbtConfig {
+            rsses: vec![
+                format!("http://{addr}/bad").into(),
+                format!("http://{addr}/missing").into(),
+            ],
+            retry: fast_retry(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+    let source = &factory.sources()[0];
+
+    assert!(source.pull_items().await.is_err());
+    assert!(source.feeds().status().iter().all(|s| s.failures == 1));
+
+    // 5xx is retried, 404 is permanent.
+    let requests = requests.lock().unwrap();
+    let count = |path: &str| requests.iter().filter(|r| r.starts_with(path)).count();
+    assert_eq!(count("GET /bad "), 3);
+    assert_eq!(count("GET /missing "), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_conditional_get() -> Result<()> {
+    let (addr, requests) = serve().await?;
+    let config = Config {
+        byrbt: Some(ByrbtConfig {
+            rsses: vec![
+                format!("http://{addr}/ok").into(),
+                format!("http://{addr}/limited").into(),
+            ],
+            retry: fast_retry(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+    let source = &factory.sources()[0];
+
+    let items = source.pull_items().await?;
+    assert_eq!(items.len(), 2);
+    let status = source.feeds().status();
+    assert_eq!(status[0].etag.as_deref(), Some("\"v1\""));
+    assert_eq!(
+        status[0].last_modified.as_deref(),
+        Some("Tue, 24 Jan 2023 13:29:39 GMT")
+    );
+    // Rate limited feeds are not retried.
+    assert_eq!(status[1].failures, 1);
+    assert!(status[1].retry_after.unwrap() > Local::now() + Duration::seconds(100));
+    assert_eq!(requests.lock().unwrap().len(), 2);
+
+    // Not modified, and the rate limited feed is skipped.
+    let items = source.pull_items().await?;
+    assert!(items.is_empty());
+    let status = source.feeds().status();
+    assert_eq!(status[0].failures, 0);
+    assert_eq!(status[0].items, 0);
+    assert_eq!(status[0].etag.as_deref(), Some("\"v1\""));
+    assert_eq!(status[1].failures, 1);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 3);
+    assert!(requests[0].contains("accept-encoding: gzip, br\r\n"));
+    assert!(requests[2].starts_with("GET /ok "));
+    assert!(requests[2].contains("if-none-match: \"v1\"\r\n"));
+    assert!(requests[2].contains("if-modified-since: Tue, 24 Jan 2023 13:29:39 GMT\r\n"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_feed_metrics() -> Result<()> {
+    let (addr, _) = serve().await?;
+    let urls = ["ok", "bad", "invalid"].map(|path| format!("http://{addr}/{path}"));
+    let config = Config {
+        byrbt: Some(ByrbtConfig {
+            rsses: urls.iter().map(|url| url.clone().into()).collect(),
+            retry: fast_retry(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+    let source = &factory.sources()[0];
+    source.pull_items().await?;
+
+    // The port is unique to the test, so are the labels.
+    let [ok, bad, invalid] = urls.map(|url| redact_url(&url));
+    let get = |name: &str, feed: &str, extra: &[(&str, &str)]| {
+        let labels = [&[("source", "BYRBT"), ("feed", feed)][..], extra].concat();
+        metrics().get(name, &labels)
+    };
+    let responses = "blooming_http_responses_total";
+    assert_eq!(get(responses, &ok, &[("class", "2xx")]), Some(1.0));
+    assert_eq!(get(responses, &bad, &[("class", "5xx")]), Some(3.0));
+    assert_eq!(get(responses, &invalid, &[("class", "2xx")]), Some(1.0));
+    assert_eq!(get("blooming_fetch_retries_total", &bad, &[]), Some(2.0));
+    assert_eq!(get("blooming_fetch_retries_total", &ok, &[]), None);
+    assert_eq!(get("blooming_parse_errors_total", &invalid, &[]), Some(1.0));
+    assert_eq!(get("blooming_fetch_duration_seconds", &bad, &[]), Some(1.0));
+
+    let last_success = "blooming_feed_last_success_timestamp_seconds";
+    let now = Local::now().timestamp() as f64;
+    assert!(get(last_success, &ok, &[]).unwrap() > now - 60.0);
+    assert_eq!(get(last_success, &bad, &[]), None);
+    assert_eq!(get(last_success, &invalid, &[]), None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_huge_retry_after() -> Result<()> {
+    let (addr, _) = serve().await?;
+    let config = Config {
+        byrbt: Some(ByrbtConfig {
+            rsses: vec![
+                format!("http://{addr}/ok").into(),
+                format!("http://{addr}/flooded").into(),
+            ],
+            retry: fast_retry(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+    let source = &factory.sources()[0];
+
+    source.pull_items().await?;
+    // Clamped to a day instead of panicking.
+    let retry_after = source.feeds().status()[1].retry_after.unwrap();
+    assert!(retry_after > Local::now() + Duration::hours(23));
+    assert!(retry_after <= Local::now() + Duration::hours(24));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rate_limited() -> Result<()> {
+    let (addr, requests) = serve().await?;
+    let config = Config {
+        byrbt: Some(ByrbtConfig {
+            rsses: vec![
+                format!("http://{addr}/limited").into(),
+                format!("http://{addr}/busy").into(),
+            ],
+            retry: fast_retry(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+    let source = &factory.sources()[0];
+
+    assert!(source.pull_items().await.is_err());
+    // 429 without `Retry-After` backs off instead of being retried.
+    assert_eq!(requests.lock().unwrap().len(), 2);
+    let status = source.feeds().status();
+    let busy = status[1].retry_after.unwrap();
+    assert!(busy > Local::now() + Duration::seconds(50));
+    assert!(busy <= Local::now() + Duration::seconds(60));
+
+    // The source is not pulled until a feed can be pulled, and the items
+    // published meanwhile may be missing.
+    assert_eq!(source.feeds().rate_limited_until(), Some(busy));
+    assert!(source.feeds().missing_since().is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_connection() -> Result<()> {
+    // Nothing listens on the address.
+    let addr = TcpListener::bind("127.0.0.1:0").await?.local_addr()?;
+    let config = Config {
+        byrbt: Some(ByrbtConfig {
+            rsses: vec![format!("http://{addr}/torrentrss.php?passkey=secret").into()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+
+    let err = factory.sources()[0].check_connection().await.unwrap_err();
+    assert!(!format!("{err:#}").contains("secret"));
+
+    Ok(())
+}
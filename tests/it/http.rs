@@ -0,0 +1,83 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use blooming::build_client;
+use blooming::register;
+use blooming::Config;
+use blooming::HttpConfig;
+use blooming::NyaaConfig;
+use blooming::Result;
+use blooming::SourceFactory;
+
+use crate::notifier::serve;
+
+#[tokio::test]
+async fn test_source_http_options() -> Result<()> {
+    let feed = std::fs::read_to_string("tests/it/testdata/nyaa.xml")?;
+    let (addr, requests) = serve(move |_, _| ("200 OK", feed.clone())).await?;
+
+    let config = Config {
+        nyaa: Some(NyaaConfig {
+            base_url: format!("http://{addr}"),
+            http: HttpConfig {
+                timeout: Some(Duration::from_secs(5)),
+                user_agent: Some("blooming-test".to_string()),
+                headers: BTreeMap::from([("X-Api-Key".to_string(), "secret".into())]),
+                cookie: Some("uid=1; pass=xxx".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+
+    let items = factory.sources()[0].pull_items().await?;
+    assert_eq!(items.len(), 2);
+
+    let request = &requests.lock().unwrap()[0];
+    assert!(request.contains("user-agent: blooming-test\r\n"));
+    assert!(request.contains("x-api-key: secret\r\n"));
+    assert!(request.contains("cookie: uid=1; pass=xxx\r\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_build_client() -> Result<()> {
+    build_client(&HttpConfig {
+        proxy: Some("socks5h://localhost:1080".into()),
+        insecure: true,
+        cookie_store: true,
+        ..Default::default()
+    })?;
+
+    let invalid_header = HttpConfig {
+        headers: BTreeMap::from([("Bad Header".to_string(), "x".into())]),
+        ..Default::default()
+    };
+    assert!(build_client(&invalid_header).is_err());
+
+    let missing_ca = HttpConfig {
+        ca_file: Some("tests/it/testdata/missing.pem".to_string()),
+        ..Default::default()
+    };
+    assert!(build_client(&missing_ca).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use blooming::logger::format_json;
+use blooming::logger::format_text;
+use blooming::logger::Filters;
+use blooming::Config;
+use blooming::LogFormat;
+use blooming::Result;
+use log::kv::Value;
+use log::Level;
+use log::LevelFilter;
+use log::Record;
+
+#[test]
+fn test_filters() -> Result<()> {
+    let config = Config::parse(
+        r#"
+        [log]
+        format = "json"
+        level = "debug"
+
+        [log.modules]
+        "blooming::source" = "warn"
+        "blooming::source::feed" = "trace"
+        reqwest = "info"
+        "#,
+    )?
+    .log;
+    assert_eq!(config.format, LogFormat::Json);
+
+    let filters = Filters::new(&config, None);
+    assert_eq!(filters.level("blooming"), LevelFilter::Debug);
+    assert_eq!(filters.level("blooming::source::nyaa"), LevelFilter::Warn);
+    assert_eq!(filters.level("blooming::source::feed"), LevelFilter::Trace);
+    // Not a submodule.
+    assert_eq!(filters.level("blooming::sourcex"), LevelFilter::Debug);
+    assert_eq!(filters.level("reqwest::connect"), LevelFilter::Info);
+    assert_eq!(filters.level("hyper"), LevelFilter::Warn);
+    assert_eq!(filters.max(), LevelFilter::Trace);
+
+    // A single level of `RUST_LOG` is the level of blooming.
+    let filters = Filters::new(&config, Some("error"));
+    assert_eq!(filters.level("blooming::notifier"), LevelFilter::Error);
+    assert_eq!(filters.level("hyper"), LevelFilter::Warn);
+
+    let filters = Filters::new(&config, Some("off,blooming::source=info,bad=level"));
+    assert_eq!(filters.level("blooming::source::nyaa"), LevelFilter::Info);
+    assert_eq!(filters.level("blooming::notifier"), LevelFilter::Debug);
+    assert_eq!(filters.level("hyper"), LevelFilter::Off);
+
+    let filters = Filters::new(&Default::default(), None);
+    assert_eq!(filters.level("blooming"), LevelFilter::Info);
+    assert_eq!(filters.max(), LevelFilter::Info);
+
+    assert!(Config::parse("[log]\nlevel = \"loud\"").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_format() -> Result<()> {
+    let fields = [
+        ("source", Value::from("Nyaa")),
+        ("feed", Value::from("Nyaa 1080p")),
+        ("items", Value::from(2u64)),
+        ("duration_ms", Value::from(120u64)),
+    ];
+
+    let json = format_json(
+        &Record::builder()
+            .args(format_args!("Notified {} new items", 2))
+            .level(Level::Info)
+            .target("blooming")
+            .key_values(&fields)
+            .build(),
+    );
+    assert!(json.ends_with('\n'));
+    let json: serde_json::Value = serde_json::from_str(&json)?;
+    assert_eq!(json["level"], "INFO");
+    assert_eq!(json["target"], "blooming");
+    assert_eq!(json["message"], "Notified 2 new items");
+    assert_eq!(json["source"], "Nyaa");
+    assert_eq!(json["feed"], "Nyaa 1080p");
+    assert_eq!(json["items"], 2);
+    assert_eq!(json["duration_ms"], 120);
+    assert!(json["time"].as_str().is_some());
+
+    let text = format_text(
+        &Record::builder()
+            .args(format_args!("Notified {} new items", 2))
+            .level(Level::Warn)
+            .target("blooming")
+            .key_values(&fields)
+            .build(),
+        false,
+    );
+    assert!(text.ends_with(
+        " - WARN  blooming > Notified 2 new items source=Nyaa feed=\"Nyaa 1080p\" items=2 duration_ms=120\n"
+    ));
+    Ok(())
+}
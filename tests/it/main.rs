@@ -15,14 +15,41 @@
 use std::fs::File;
 use std::io::BufReader;
 
+use blooming::release::ReleaseInfo;
+use blooming::AcgRip;
 use blooming::Byrbt;
+use blooming::Dmhy;
 use blooming::Item;
 use blooming::Mikan;
+use blooming::Nyaa;
 use blooming::Result;
 use blooming::Tjupt;
+use blooming::Torznab;
 use chrono::DateTime;
 use chrono::Local;
 
+mod acgrip;
+mod check;
+mod collapse;
+mod config;
+mod daemon;
+mod dmhy;
+mod feed;
+mod http;
+mod logger;
+mod metrics;
+mod mikan;
+mod notifier;
+mod nyaa;
+mod poll;
+mod release;
+mod retry;
+mod schedule;
+mod state;
+mod supervisor;
+mod torrent;
+mod torznab;
+
 #[test]
 fn test_parse_mikan() -> Result<()> {
     test_parse_mikan_impl("tests/it/testdata/mikan_v1.xml")?;
@@ -43,6 +70,21 @@ fn test_parse_mikan_impl(path: &str) -> Result<()> {
         url:
             "https://mikanani.me/Download/20230124/5dd79686d9b6c1ab2a6091363d493d05333d8899.torrent"
                 .to_string(),
+        size: Some(454819840),
+        release: Some(ReleaseInfo {
+            group: Some("豌豆字幕组".to_string()),
+            titles: vec![
+                "海盗战记".to_string(),
+                "冰海战记 第二季".to_string(),
+                "Vinland_Saga_S2".to_string(),
+            ],
+            season: Some(2),
+            episode: Some(3),
+            resolution: Some("1080P".to_string()),
+            subtitle: Some("简体".to_string()),
+            container: Some("MP4".to_string()),
+        }),
+        ..Default::default()
     };
     let item2 = Item {
         title: "[ANi] The Vampire Dies in No Time S2 - 吸血鬼马上死 第二季 - 03".to_string(),
@@ -52,6 +94,20 @@ fn test_parse_mikan_impl(path: &str) -> Result<()> {
         url:
             "https://mikanani.me/Download/20230123/fa2fca2b18dc4d6e166cab56fd36dcb547eafe6e.torrent"
                 .to_string(),
+        size: Some(580019840),
+        release: Some(ReleaseInfo {
+            group: Some("ANi".to_string()),
+            titles: vec![
+                "The Vampire Dies in No Time S2".to_string(),
+                "吸血鬼马上死 第二季".to_string(),
+            ],
+            season: Some(2),
+            episode: Some(3),
+            resolution: Some("1080P".to_string()),
+            subtitle: Some("CHT".to_string()),
+            container: Some("MP4".to_string()),
+        }),
+        ..Default::default()
     };
     let expected = vec![item1, item2];
 
@@ -74,6 +130,9 @@ fn test_parse_byrbt() -> Result<()> {
                 .unwrap()
                 .with_timezone(&Local {}),
             url: "https://byr.pt/details.php?id=330667".to_string(),
+            enclosure: Some("link".to_string()),
+            size: Some(969608231),
+            ..Default::default()
         },
         Item {
             title: "[大陆][三体][Three.Body.S01.2023.2160p.DV.WEB-DL.H265.DDP5.1.Atmos-CHDWEB]"
@@ -82,10 +141,16 @@ fn test_parse_byrbt() -> Result<()> {
                 .unwrap()
                 .with_timezone(&Local {}),
             url: "https://byr.pt/details.php?id=330666".to_string(),
+            enclosure: Some("link".to_string()),
+            size: Some(4701677525),
+            ..Default::default()
         },
     ];
 
     assert_eq!(items, expected);
+    // The download links contain passkeys.
+    let json = serde_json::to_string(&items)?;
+    assert!(!json.contains("enclosure"));
 
     Ok(())
 }
@@ -102,9 +167,77 @@ fn test_parse_tjubt() -> Result<()> {
             .unwrap()
             .with_timezone(&Local {}),
         url: "https://www.tjupt.org/details.php?id=242844&hit=1".to_string(),
+        ..Default::default()
     }];
 
     assert_eq!(items, expected);
 
     Ok(())
 }
+
+#[test]
+fn test_skip_broken_items() -> Result<()> {
+    let content = br#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <item>
+      <title>[SubsPlease] Vinland Saga S2 - 03 (1080p)</title>
+      <link>https://example.com/3</link>
+      <pubDate>Tue, 24 Jan 2023 13:29:39 +0000</pubDate>
+    </item>
+    <item>
+      <title>No date</title>
+      <link>https://example.com/no-date</link>
+    </item>
+    <item>
+      <title>Bad date</title>
+      <link>https://example.com/bad-date</link>
+      <pubDate>yesterday</pubDate>
+    </item>
+    <item>
+      <link>https://example.com/no-title</link>
+      <pubDate>Tue, 24 Jan 2023 13:29:39 +0000</pubDate>
+    </item>
+    <item>
+      <title>No link</title>
+      <pubDate>Tue, 24 Jan 2023 13:29:39 +0000</pubDate>
+    </item>
+  </channel>
+</rss>"#;
+
+    let titles = |items: Vec<Item>| items.into_iter().map(|i| i.title).collect::<Vec<_>>();
+    let good = vec!["[SubsPlease] Vinland Saga S2 - 03 (1080p)".to_string()];
+    assert_eq!(titles(Nyaa::parse_items(&content[..])?), good);
+    assert_eq!(titles(Dmhy::parse_items(&content[..])?), good);
+    assert_eq!(titles(AcgRip::parse_items(&content[..])?), good);
+    assert_eq!(titles(Byrbt::parse_items(&content[..])?), good);
+    assert_eq!(titles(Tjupt::parse_items(&content[..])?), good);
+    // Torznab items may have no link.
+    let mut with_link = good;
+    with_link.push("No link".to_string());
+    assert_eq!(titles(Torznab::parse_items(&content[..])?), with_link);
+
+    let content = r#"<rss version="2.0">
+  <channel>
+    <item>
+      <title>[ANi] Vinland Saga S2 - 03</title>
+      <torrent xmlns="https://mikanime.tv/0.1/"><pubDate>2023-01-24T14:34:31.721</pubDate></torrent>
+      <enclosure type="application/x-bittorrent" length="1" url="https://mikanani.me/1.torrent"/>
+    </item>
+    <item>
+      <title>No torrent</title>
+      <torrent xmlns="https://mikanime.tv/0.1/"><pubDate>2023-01-24T14:34:31.721</pubDate></torrent>
+    </item>
+    <item>
+      <title>No date</title>
+      <enclosure type="application/x-bittorrent" length="1" url="https://mikanani.me/2.torrent"/>
+    </item>
+  </channel>
+</rss>"#;
+    let items = Mikan::parse_items(content.as_bytes())?;
+    assert_eq!(titles(items), vec![
+        "[ANi] Vinland Saga S2 - 03".to_string()
+    ]);
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use blooming::metrics;
+use blooming::metrics::Metrics;
+use blooming::Result;
+use reqwest::StatusCode;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+
+#[test]
+fn test_histogram() {
+    let m = Metrics::default();
+    for value in [0.01, 0.3, 7.0, 500.0] {
+        m.observe("fetch_seconds", "Test.", &[("source", "Nyaa")], value);
+    }
+    assert_eq!(m.get("fetch_seconds", &[("source", "Nyaa")]), Some(4.0));
+
+    let text = m.render();
+    assert!(text.contains("# TYPE fetch_seconds histogram\n"));
+    assert!(text.contains("fetch_seconds_bucket{source=\"Nyaa\",le=\"0.05\"} 1\n"));
+    assert!(text.contains("fetch_seconds_bucket{source=\"Nyaa\",le=\"0.5\"} 2\n"));
+    assert!(text.contains("fetch_seconds_bucket{source=\"Nyaa\",le=\"10\"} 3\n"));
+    assert!(text.contains("fetch_seconds_bucket{source=\"Nyaa\",le=\"120\"} 3\n"));
+    assert!(text.contains("fetch_seconds_bucket{source=\"Nyaa\",le=\"+Inf\"} 4\n"));
+    assert!(text.contains("fetch_seconds_sum{source=\"Nyaa\"} 507.31\n"));
+    assert!(text.contains("fetch_seconds_count{source=\"Nyaa\"} 4\n"));
+}
+
+#[test]
+fn test_counter() {
+    let m = Metrics::default();
+    let labels = [
+        ("notifier", "QQ"),
+        ("target", "group 1"),
+        ("result", "sent"),
+    ];
+    m.inc_counter("sent_total", "Test.", &labels, 1.0);
+    m.inc_counter("sent_total", "Test.", &labels, 2.0);
+    assert_eq!(m.get("sent_total", &labels), Some(3.0));
+
+    let text = m.render();
+    assert!(text.contains("# TYPE sent_total counter\n"));
+    assert!(text.contains("sent_total{notifier=\"QQ\",result=\"sent\",target=\"group 1\"} 3\n"));
+}
+
+#[tokio::test]
+async fn test_serve() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let server = tokio::spawn(metrics::serve(listener, metrics::REQUEST_TIMEOUT));
+    metrics::metrics().set_gauge("test_serve_up", "Test.", &[], 1.0);
+
+    let resp = reqwest::get(format!("http://{addr}/metrics")).await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(
+        resp.headers()["content-type"]
+            .to_str()?
+            .starts_with("text/plain; version=0.0.4")
+    );
+    let body = resp.text().await?;
+    assert!(body.contains("# TYPE test_serve_up gauge\ntest_serve_up 1\n"));
+
+    let resp = reqwest::get(format!("http://{addr}/metrics?name[]=test_serve_up")).await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = reqwest::get(format!("http://{addr}/other")).await?;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = reqwest::Client::new()
+        .post(format!("http://{addr}/metrics"))
+        .send()
+        .await?;
+    assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_serve_timeout() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let server = tokio::spawn(metrics::serve(listener, Duration::from_millis(100)));
+
+    // A client that never finishes the request line is disconnected.
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(b"GET /metr").await?;
+    let mut buf = vec![];
+    let read = tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut buf)).await;
+    assert!(matches!(read, Ok(Ok(0))));
+
+    server.abort();
+    Ok(())
+}
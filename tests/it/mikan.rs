@@ -0,0 +1,71 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use blooming::register;
+use blooming::Config;
+use blooming::MikanBangumiConfig;
+use blooming::MikanConfig;
+use blooming::Result;
+use blooming::SourceFactory;
+
+use crate::notifier::serve;
+
+#[tokio::test]
+async fn test_mikan_subscriptions() -> Result<()> {
+    // Every feed returns the same items.
+    let feed = std::fs::read_to_string("tests/it/testdata/mikan_v1.xml")?;
+    let (addr, _) = serve(move |_, _| ("200 OK", feed.clone())).await?;
+
+    let config = Config {
+        mikan: Some(MikanConfig {
+            base_url: format!("http://{addr}/"),
+            bangumis: vec![
+                MikanBangumiConfig {
+                    id: 2914,
+                    subgroup: Some(370),
+                    label: Some("Vinland Saga".to_string()),
+                },
+                MikanBangumiConfig {
+                    id: 2923,
+                    ..Default::default()
+                },
+            ],
+            keywords: vec!["海盗战记".to_string()],
+            classic: true,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+    let source = &factory.sources()[0];
+
+    assert_eq!(source.rsses(), vec![
+        format!("http://{addr}/RSS/Bangumi?bangumiId=2914&subgroupid=370"),
+        format!("http://{addr}/RSS/Bangumi?bangumiId=2923"),
+        format!("http://{addr}/RSS/Search?searchstr=%E6%B5%B7%E7%9B%97%E6%88%98%E8%AE%B0"),
+        format!("http://{addr}/RSS/Classic"),
+    ]);
+
+    let items = source.pull_items().await?;
+    assert_eq!(items.len(), 2);
+    for item in items {
+        assert_eq!(
+            item.label.as_deref(),
+            Some("Vinland Saga, Bangumi 2923, Search 海盗战记, Classic")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,214 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use blooming::metrics::metrics;
+use blooming::Item;
+use blooming::Notifier;
+use blooming::QQBotConfig;
+use blooming::QQGuildBotConfig;
+use blooming::QQGuildNotifier;
+use blooming::QQNotifier;
+use blooming::Result;
+use blooming::RetryConfig;
+use chrono::Local;
+use reqwest::Client;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+
+pub(crate) type Requests = Arc<Mutex<Vec<String>>>;
+
+/// Serve the responses of `respond` to the request line and the body, and record
+/// the requests.
+pub(crate) async fn serve(
+    respond: impl Fn(&str, &str) -> (&'static str, String) + Send + 'static,
+) -> Result<(SocketAddr, Requests)> {
+    serve_http(move |head, body| {
+        let line = head.lines().next().unwrap_or_default();
+        let (status, body) = respond(line, body);
+        (
+            status,
+            "Content-Type: application/json\r\n".to_string(),
+            body,
+        )
+    })
+    .await
+}
+
+/// Serve the responses of `respond` to the request head, ie. the request line and
+/// the headers, and the body, and record the requests. The responses are the status,
+/// the headers, each ending with `\r\n`, and the body.
+pub(crate) async fn serve_http(
+    respond: impl Fn(&str, &str) -> (&'static str, String, String) + Send + 'static,
+) -> Result<(SocketAddr, Requests)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let requests = Arc::new(Mutex::new(vec![]));
+    let recorded = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let Some((head, body)) = read_request(&mut stream).await else {
+                continue;
+            };
+            recorded
+                .lock()
+                .unwrap()
+                .push(format!("{head}\r\n\r\n{body}"));
+            let (status, headers, body) = respond(&head, &body);
+            let response = format!(
+                "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        }
+    });
+    Ok((addr, requests))
+}
+
+/// The request head and the body.
+async fn read_request(stream: &mut TcpStream) -> Option<(String, String)> {
+    let mut buf = vec![];
+    let mut chunk = vec![0; 4096];
+    loop {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        let request = String::from_utf8_lossy(&buf).to_string();
+        let Some((head, body)) = request.split_once("\r\n\r\n") else {
+            continue;
+        };
+        let length = head
+            .lines()
+            .find_map(|l| {
+                l.to_lowercase()
+                    .strip_prefix("content-length: ")?
+                    .parse()
+                    .ok()
+            })
+            .unwrap_or(0);
+        if body.len() >= length {
+            return Some((head.to_string(), body.to_string()));
+        }
+    }
+}
+
+fn item(title: &str) -> Item {
+    Item {
+        title: title.to_string(),
+        pub_date: Local::now(),
+        ..Default::default()
+    }
+}
+
+fn fast_retry() -> RetryConfig {
+    RetryConfig {
+        delay: Duration::from_millis(10),
+        max_times: 2,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_qq_failed_target() -> Result<()> {
+    // The bot has left group 7002.
+    let (addr, requests) = serve(|_, body| {
+        if body.contains(r#""group_id":7002"#) {
+            let resp = r#"{"status":"failed","retcode":100,"wording":"group not found"}"#;
+            ("200 OK", resp.to_string())
+        } else {
+            ("200 OK", r#"{"status":"ok","retcode":0}"#.to_string())
+        }
+    })
+    .await?;
+    let mut notifier = QQNotifier::new(Client::new(), QQBotConfig {
+        api: format!("http://{addr}"),
+        groups: vec![7001, 7002, 7003],
+        retry: fast_retry(),
+        ..Default::default()
+    });
+
+    notifier
+        .notify("Nyaa", vec![item("Vinland Saga - 03")])
+        .await?;
+
+    // The groups after the failed one are still sent.
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 3);
+    for group in [7001, 7002, 7003] {
+        let sent = format!(r#""group_id":{group}"#);
+        assert!(requests.iter().any(|r| r.contains(&sent)));
+    }
+    let count = |target: &str, result: &str| {
+        let labels = [("notifier", "QQ"), ("target", target), ("result", result)];
+        metrics().get("blooming_notifications_total", &labels)
+    };
+    assert_eq!(count("group 7001", "sent"), Some(1.0));
+    assert_eq!(count("group 7002", "failed"), Some(1.0));
+    assert_eq!(count("group 7003", "sent"), Some(1.0));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_qq_guild_retry_server_error() -> Result<()> {
+    // The first message fails with 500, which is not an expired token.
+    let sent = AtomicUsize::new(0);
+    let (addr, requests) = serve(move |line, _| {
+        if line.starts_with("POST /token ") {
+            let token = r#"{"access_token":"token","expires_in":"7200"}"#;
+            return ("200 OK", token.to_string());
+        }
+        if sent.fetch_add(1, Ordering::SeqCst) == 0 {
+            let error = r#"{"message":"internal error","code":500}"#;
+            ("500 Internal Server Error", error.to_string())
+        } else {
+            ("200 OK", r#"{"id":"1"}"#.to_string())
+        }
+    })
+    .await?;
+    let mut notifier = QQGuildNotifier::new(Client::new(), QQGuildBotConfig {
+        channel_id: "8001".to_string(),
+        retry: fast_retry(),
+        ..Default::default()
+    })
+    .with_endpoints(&format!("http://{addr}"), &format!("http://{addr}/token"));
+
+    notifier
+        .notify("Nyaa", vec![item("Vinland Saga - 03")])
+        .await?;
+
+    let requests = requests.lock().unwrap();
+    let messages = requests
+        .iter()
+        .filter(|r| r.starts_with("POST /channels/8001/messages "))
+        .count();
+    assert_eq!(messages, 2);
+    let labels = [("notifier", "QQ Guild"), ("target", "channel 8001")];
+    assert_eq!(
+        metrics().get("blooming_notification_retries_total", &labels),
+        Some(1.0)
+    );
+
+    Ok(())
+}
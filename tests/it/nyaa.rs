@@ -0,0 +1,118 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+
+use blooming::register;
+use blooming::Config;
+use blooming::Nyaa;
+use blooming::NyaaConfig;
+use blooming::Result;
+use blooming::SourceFactory;
+
+use crate::notifier::serve;
+
+#[test]
+fn test_parse_nyaa() -> Result<()> {
+    let file = File::open("tests/it/testdata/nyaa.xml")?;
+    let items = Nyaa::parse_items(BufReader::new(file))?;
+    assert_eq!(items.len(), 2);
+
+    let item = &items[0];
+    assert_eq!(
+        item.title,
+        "[SubsPlease] Vinland Saga S2 - 03 (1080p) [5DD79686].mkv"
+    );
+    assert_eq!(item.url, "https://nyaa.si/download/1631820.torrent");
+    assert_eq!(item.seeders, Some(1523));
+    assert_eq!(item.leechers, Some(42));
+    assert_eq!(item.downloads, Some(20531));
+    assert_eq!(
+        item.info_hash.as_deref(),
+        Some("5dd79686d9b6c1ab2a6091363d493d05333d8899")
+    );
+    assert_eq!(item.category.as_deref(), Some("Anime - English-translated"));
+    assert_eq!(item.size, Some(1503238553));
+    assert_eq!(
+        item.attrs,
+        BTreeMap::from([
+            ("categoryId".to_string(), "1_2".to_string()),
+            ("remake".to_string(), "No".to_string()),
+            ("trusted".to_string(), "Yes".to_string()),
+        ])
+    );
+    let release = item.release.as_ref().unwrap();
+    assert_eq!(release.group.as_deref(), Some("SubsPlease"));
+    assert_eq!(release.episode, Some(3));
+    assert_eq!(
+        item.magnet().unwrap(),
+        "magnet:?xt=urn:btih:5dd79686d9b6c1ab2a6091363d493d05333d8899\
+        &dn=%5BSubsPlease%5D%20Vinland%20Saga%20S2%20-%2003%20%281080p%29%20%5B5DD79686%5D.mkv"
+    );
+
+    let item = &items[1];
+    assert_eq!(item.attrs["remake"], "Yes");
+    assert_eq!(item.size, Some(863502336));
+    assert_eq!(item.release.as_ref().unwrap().season, Some(2));
+
+    Ok(())
+}
+
+#[test]
+fn test_nyaa_rsses() -> Result<()> {
+    let config = Config {
+        nyaa: Some(NyaaConfig {
+            base_url: "http://localhost:8080/nyaa/".to_string(),
+            query: "vinland saga 1080p".to_string(),
+            categories: vec!["1_2".to_string(), "1_4".to_string()],
+            filter: 2,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+
+    assert_eq!(factory.sources()[0].rsses(), vec![
+        "http://localhost:8080/nyaa/?page=rss&q=vinland+saga+1080p&c=1_2&f=2",
+        "http://localhost:8080/nyaa/?page=rss&q=vinland+saga+1080p&c=1_4&f=2",
+    ]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_nyaa_dedup() -> Result<()> {
+    let feed = std::fs::read_to_string("tests/it/testdata/nyaa.xml")?;
+    let (addr, requests) = serve(move |_, _| ("200 OK", feed.clone())).await?;
+    let config = Config {
+        nyaa: Some(NyaaConfig {
+            base_url: format!("http://{addr}/"),
+            categories: vec!["1_2".to_string(), "1_4".to_string()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+
+    // Both categories list the same releases, which are only returned once.
+    let items = factory.sources()[0].pull_items().await?;
+    assert_eq!(requests.lock().unwrap().len(), 2);
+    assert_eq!(items.len(), 2);
+
+    Ok(())
+}
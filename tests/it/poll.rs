@@ -0,0 +1,407 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use blooming::poll::create_sources;
+use blooming::poll::deliver;
+use blooming::poll::fetch;
+use blooming::poll::once;
+use blooming::poll::Poller;
+use blooming::release::ReleaseInfo;
+use blooming::state::StateStore;
+use blooming::CollapseConfig;
+use blooming::Collapser;
+use blooming::Config;
+use blooming::HeldEpisode;
+use blooming::Item;
+use blooming::QQNotifier;
+use blooming::Result;
+use chrono::DateTime;
+use chrono::Local;
+use reqwest::Client;
+
+use crate::notifier::serve;
+use crate::notifier::Requests;
+
+/// Serve the BYRBT and TJUPT fixtures at `/byrbt` and `/tjupt`, and accept every
+/// message of go-cqhttp.
+async fn serve_feed() -> Result<(SocketAddr, Requests)> {
+    let byrbt = std::fs::read_to_string("tests/it/testdata/byrbt.xml")?;
+    let tjupt = std::fs::read_to_string("tests/it/testdata/tjupt.xml")?;
+    serve(move |line, _| {
+        if line.starts_with("GET /byrbt ") {
+            ("200 OK", byrbt.clone())
+        } else if line.starts_with("GET /tjupt ") {
+            ("200 OK", tjupt.clone())
+        } else {
+            ("200 OK", r#"{"status":"ok","retcode":0}"#.to_string())
+        }
+    })
+    .await
+}
+
+fn config(addr: SocketAddr, state_file: &str) -> Result<Config> {
+    Config::parse(&format!(
+        r#"
+        state_file = "{state_file}"
+
+        [byrbt]
+        interval = 60
+        rsses = ["http://{addr}/byrbt"]
+
+        [qq]
+        api = "http://{addr}"
+        groups = [1]
+        delay = 0
+        "#
+    ))
+}
+
+fn date(s: &str) -> DateTime<Local> {
+    DateTime::parse_from_rfc3339(s).unwrap().into()
+}
+
+fn count(requests: &Requests, prefix: &str) -> usize {
+    let requests = requests.lock().unwrap();
+    requests.iter().filter(|r| r.starts_with(prefix)).count()
+}
+
+#[tokio::test]
+async fn test_once() -> Result<()> {
+    let (addr, requests) = serve_feed().await?;
+    let path = std::env::temp_dir().join(format!("blooming-once-{}.json", std::process::id()));
+    let state_file = path.to_str().unwrap();
+    let _ = std::fs::remove_file(&path);
+    // Between the two items of the feed.
+    StateStore::open(Some(state_file))?.update(|state| {
+        state
+            .cursors
+            .insert("byrbt/qq".to_string(), date("2023-01-24T21:00:00+08:00"))
+    })?;
+    let saved = std::fs::read(&path)?;
+
+    // Dry runs send nothing and save nothing.
+    once(config(addr, state_file)?, true).await?;
+    assert_eq!(count(&requests, "GET /byrbt "), 1);
+    assert_eq!(count(&requests, "POST "), 0);
+    assert_eq!(std::fs::read(&path)?, saved);
+
+    // Only the item after the cursor is sent, and the cursor moves to it.
+    once(config(addr, state_file)?, false).await?;
+    assert_eq!(count(&requests, "GET /byrbt "), 2);
+    assert_eq!(count(&requests, "POST "), 1);
+    let cursor =
+        StateStore::open(Some(state_file))?.read(|state| state.cursors.get("byrbt/qq").copied());
+    assert_eq!(cursor, Some(date("2023-01-24T21:29:39+08:00")));
+
+    // Nothing is new in the next run.
+    once(config(addr, state_file)?, false).await?;
+    assert_eq!(count(&requests, "GET /byrbt "), 3);
+    assert_eq!(count(&requests, "POST "), 1);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fetch() -> Result<()> {
+    let (addr, requests) = serve_feed().await?;
+    let config = config(addr, "unused.json")?;
+
+    let items = fetch(&config, "byrbt").await?;
+    let dates = items.iter().map(|item| item.pub_date).collect::<Vec<_>>();
+    assert_eq!(dates, vec![
+        date("2023-01-24T21:29:39+08:00"),
+        date("2023-01-24T20:21:43+08:00"),
+    ]);
+
+    let err = fetch(&config, "nyaa").await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Source 'nyaa' is not configured, the configured: byrbt"
+    );
+    assert_eq!(count(&requests, "GET "), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reload() -> Result<()> {
+    let (addr, _) = serve_feed().await?;
+    let config = |tjupt_interval: u64, groups: &str, tjupt: bool| {
+        let mut config = format!(
+            r#"
+            [byrbt]
+            interval = 60
+            rsses = ["http://{addr}/byrbt"]
+
+            [qq]
+            api = "http://{addr}"
+            groups = {groups}
+            delay = 0
+            "#
+        );
+        if tjupt {
+            config.push_str(&format!(
+                "[tjupt]\ninterval = {tjupt_interval}\nrsses = [\"http://{addr}/tjupt\"]\n"
+            ));
+        }
+        Config::parse(&config)
+    };
+    let both = vec![("byrbt", "qq"), ("tjupt", "qq")];
+
+    let mut poller = Poller::start(config(60, "[1]", true)?, false)?;
+    assert_eq!(poller.tasks(), both);
+
+    let started = poller.reload(config(60, "[1]", true)?).await?;
+    assert!(started.is_empty());
+
+    // The stopped tasks are cancelled instead of waiting for the shutdown timeout.
+    let started = poller.reload(config(120, "[1]", true)?).await?;
+    assert_eq!(started, vec![("tjupt", "qq")]);
+    assert_eq!(poller.tasks(), both);
+
+    let started = poller.reload(config(120, "[1, 2]", true)?).await?;
+    assert_eq!(started, both);
+
+    let started = poller.reload(config(120, "[1, 2]", false)?).await?;
+    assert!(started.is_empty());
+    assert_eq!(poller.tasks(), vec![("byrbt", "qq")]);
+
+    // Configs whose sources cannot be created keep the tasks running.
+    let invalid = Config::parse(
+        r#"
+        [byrbt]
+        interval = 60
+        rsses = ["http://127.0.0.1/byrbt"]
+
+        [byrbt.http]
+        headers = { "Bad Header" = "x" }
+        "#,
+    )?;
+    assert!(poller.reload(invalid).await.is_err());
+    assert_eq!(poller.tasks(), vec![("byrbt", "qq")]);
+
+    poller.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_deliver_held() -> Result<()> {
+    let (addr, requests) = serve_feed().await?;
+    let config = config(addr, "unused.json")?;
+    let source = create_sources(&config)?.remove("byrbt").unwrap();
+    let mut notifier = QQNotifier::new(Client::new(), config.qq.clone().unwrap());
+    let state = StateStore::open(None)?;
+    let cursor = "byrbt/qq";
+    state.update(|state| {
+        let date = date("2023-01-24T21:00:00+08:00");
+        state.cursors.insert(cursor.to_string(), date)
+    })?;
+    let conf = CollapseConfig {
+        window: Duration::from_secs(600),
+        groups: vec!["LoliHouse".to_string()],
+        ..Default::default()
+    };
+    let item = |title: &str, pub_date: &str| Item {
+        title: title.to_string(),
+        pub_date: date(pub_date),
+        release: Some(ReleaseInfo::parse(title)),
+        ..Default::default()
+    };
+    let items = vec![
+        item(
+            "[ANi] Vinland Saga S2 - 03 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+            "2023-01-24T21:10:00+08:00",
+        ),
+        item(
+            "[LoliHouse] Vinland Saga S2 - 03 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]",
+            "2023-01-24T21:20:00+08:00",
+        ),
+    ];
+
+    // The held releases are saved with the cursor moved past them.
+    let mut collapser = Collapser::new(conf.clone());
+    let new_items = deliver(
+        &source,
+        items,
+        &mut notifier,
+        &config,
+        &state,
+        cursor,
+        Some(&mut collapser),
+    )
+    .await?;
+    assert_eq!(new_items, 2);
+    assert_eq!(count(&requests, "POST "), 0);
+    let (cursor_date, held) =
+        state.read(|state| (state.cursors[cursor], state.held[cursor].clone()));
+    assert_eq!(cursor_date, date("2023-01-24T21:20:00+08:00"));
+    assert_eq!(held.len(), 1);
+    assert_eq!(held[0].items.len(), 2);
+
+    // A restarted task holds them again, until the window expires.
+    for (age, sent) in [(5, 0), (11, 1)] {
+        state.update(|state| {
+            let held = state.held.get_mut(cursor).unwrap();
+            held[0].first_seen = Local::now() - chrono::Duration::minutes(age);
+        })?;
+        let mut collapser = Collapser::new(conf.clone());
+        collapser.restore(
+            state.read(|state| state.held[cursor].clone()),
+            Instant::now(),
+        );
+        let collapser = Some(&mut collapser);
+        deliver(
+            &source,
+            vec![],
+            &mut notifier,
+            &config,
+            &state,
+            cursor,
+            collapser,
+        )
+        .await?;
+        assert_eq!(count(&requests, "POST "), sent);
+    }
+    assert!(state.read(|state| state.held.is_empty()));
+    assert!(
+        requests
+            .lock()
+            .unwrap()
+            .last()
+            .unwrap()
+            .contains("[LoliHouse]")
+    );
+
+    // They are sent as they are once the collapser is disabled.
+    state.update(|state| {
+        let held = HeldEpisode {
+            first_seen: Local::now(),
+            items: vec![item(
+                "[ANi] Vinland Saga S2 - 04 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+                "2023-01-24T21:15:00+08:00",
+            )],
+        };
+        state.held.insert(cursor.to_string(), vec![held])
+    })?;
+    let new_items = deliver(
+        &source,
+        vec![],
+        &mut notifier,
+        &config,
+        &state,
+        cursor,
+        None,
+    )
+    .await?;
+    assert_eq!(new_items, 0);
+    assert_eq!(count(&requests, "POST "), 2);
+    assert!(requests.lock().unwrap().last().unwrap().contains("- 04"));
+    assert!(state.read(|state| state.held.is_empty()));
+
+    Ok(())
+}
+
+/// A NexusPHP feed with an item of each `(title, pub_date)`.
+fn nexus_feed(items: &[(&str, &str)]) -> String {
+    let items = items
+        .iter()
+        .map(|(title, date)| {
+            format!(
+                "<item><title>{title}</title><link>https://byr.pt/details.php?id=1</link>\
+                 <enclosure url=\"link\" length=\"1\" type=\"application/x-bittorrent\"/>\
+                 <pubDate>{date}</pubDate></item>"
+            )
+        })
+        .collect::<String>();
+    format!("<rss version=\"2.0\"><channel><title>BYRBT Torrents</title>{items}</channel></rss>")
+}
+
+#[tokio::test]
+async fn test_failed_feed() -> Result<()> {
+    let recovered = Arc::new(AtomicBool::new(false));
+    let byrbt = std::fs::read_to_string("tests/it/testdata/byrbt.xml")?;
+    let flaky = nexus_feed(&[("Older", "Tue, 24 Jan 2023 20:50:00 +0800")]);
+    let (addr, requests) = serve({
+        let recovered = recovered.clone();
+        move |line, _| {
+            if line.starts_with("GET /byrbt ") {
+                ("200 OK", byrbt.clone())
+            } else if line.starts_with("GET /flaky ") {
+                match recovered.load(Ordering::SeqCst) {
+                    true => ("200 OK", flaky.clone()),
+                    false => ("404 Not Found", String::new()),
+                }
+            } else {
+                ("200 OK", r#"{"status":"ok","retcode":0}"#.to_string())
+            }
+        }
+    })
+    .await?;
+    let path = std::env::temp_dir().join(format!("blooming-flaky-{}.json", std::process::id()));
+    let state_file = path.to_str().unwrap();
+    let _ = std::fs::remove_file(&path);
+    StateStore::open(Some(state_file))?.update(|state| {
+        let date = date("2023-01-24T20:00:00+08:00");
+        state.cursors.insert("byrbt/qq".to_string(), date)
+    })?;
+    let config = || {
+        Config::parse(&format!(
+            r#"
+            state_file = "{state_file}"
+
+            [byrbt]
+            interval = 60
+            rsses = ["http://{addr}/byrbt", "http://{addr}/flaky"]
+
+            [qq]
+            api = "http://{addr}"
+            groups = [1]
+            delay = 0
+            "#
+        ))
+    };
+    let state = || StateStore::open(Some(state_file));
+
+    // The cursor stays while a feed fails, the items sent are remembered.
+    once(config()?, false).await?;
+    assert_eq!(count(&requests, "POST "), 1);
+    let (cursor, notified) =
+        state()?.read(|state| (state.cursors["byrbt/qq"], state.notified["byrbt/qq"].len()));
+    assert_eq!(cursor, date("2023-01-24T20:00:00+08:00"));
+    assert_eq!(notified, 2);
+
+    // Older items of the recovered feed are still sent, and only them.
+    recovered.store(true, Ordering::SeqCst);
+    once(config()?, false).await?;
+    assert_eq!(count(&requests, "POST "), 2);
+    assert!(requests.lock().unwrap().last().unwrap().contains("Older"));
+    let (cursor, notified) =
+        state()?.read(|state| (state.cursors["byrbt/qq"], state.notified.is_empty()));
+    assert_eq!(cursor, date("2023-01-24T21:29:39+08:00"));
+    assert!(notified);
+
+    once(config()?, false).await?;
+    assert_eq!(count(&requests, "POST "), 2);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,113 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use blooming::release::ReleaseInfo;
+
+fn titles(titles: &[&str]) -> Vec<String> {
+    titles.iter().map(|t| t.to_string()).collect()
+}
+
+#[test]
+fn test_parse_bracket_style() {
+    let info = ReleaseInfo::parse(
+        "【豌豆字幕组】[海盗战记 / 冰海战记 第二季 / Vinland_Saga_S2][03][简体][1080P][MP4]",
+    );
+    let expected = ReleaseInfo {
+        group: Some("豌豆字幕组".to_string()),
+        titles: titles(&["海盗战记", "冰海战记 第二季", "Vinland_Saga_S2"]),
+        season: Some(2),
+        episode: Some(3),
+        resolution: Some("1080P".to_string()),
+        subtitle: Some("简体".to_string()),
+        container: Some("MP4".to_string()),
+    };
+    assert_eq!(info, expected);
+    assert_eq!(
+        info.series_names(),
+        titles(&["海盗战记", "冰海战记", "vinland saga"])
+    );
+}
+
+#[test]
+fn test_parse_dash_style() {
+    let info = ReleaseInfo::parse(
+        "[ANi] The Vampire Dies in No Time S2 - 吸血鬼马上死 第二季 - 03 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]",
+    );
+    let expected = ReleaseInfo {
+        group: Some("ANi".to_string()),
+        titles: titles(&["The Vampire Dies in No Time S2", "吸血鬼马上死 第二季"]),
+        season: Some(2),
+        episode: Some(3),
+        resolution: Some("1080P".to_string()),
+        subtitle: Some("CHT".to_string()),
+        container: Some("MP4".to_string()),
+    };
+    assert_eq!(info, expected);
+
+    let info =
+        ReleaseInfo::parse("[ANi] The Vampire Dies in No Time S2 - 吸血鬼马上死 第二季 - 03");
+    assert_eq!(info.episode, Some(3));
+    assert_eq!(info.season, Some(2));
+    assert_eq!(info.resolution, None);
+}
+
+#[test]
+fn test_parse_chinese_numbers() {
+    let info = ReleaseInfo::parse(
+        "【喵萌奶茶屋】★04月新番★[夏日重现 第二季][第十二话][1080p][简日双语][招募翻译]",
+    );
+    assert_eq!(info.group.as_deref(), Some("喵萌奶茶屋"));
+    assert_eq!(info.titles, titles(&["夏日重现 第二季"]));
+    assert_eq!(info.season, Some(2));
+    assert_eq!(info.episode, Some(12));
+    assert_eq!(info.subtitle.as_deref(), Some("简日双语"));
+
+    let info = ReleaseInfo::parse(
+        "[桜都字幕组] 鬼灭之刃 第三季 / Kimetsu no Yaiba 3rd Season [第二十四集][1920x1080][简繁内封]",
+    );
+    assert_eq!(
+        info.titles,
+        titles(&["鬼灭之刃 第三季", "Kimetsu no Yaiba 3rd Season"])
+    );
+    assert_eq!(info.season, Some(3));
+    assert_eq!(info.episode, Some(24));
+    assert_eq!(info.resolution.as_deref(), Some("1080P"));
+    assert_eq!(
+        info.series_names(),
+        titles(&["鬼灭之刃", "kimetsu no yaiba"])
+    );
+}
+
+#[test]
+fn test_parse_other_styles() {
+    let info = ReleaseInfo::parse("[Nekomoe kissaten][Vinland Saga S2][03v2][1080p][JPSC].mkv");
+    assert_eq!(info.group.as_deref(), Some("Nekomoe kissaten"));
+    assert_eq!(info.titles, titles(&["Vinland Saga S2"]));
+    assert_eq!(info.episode, Some(3));
+    assert_eq!(info.subtitle.as_deref(), Some("JPSC"));
+
+    let info = ReleaseInfo::parse("[SubsPlease] Spy x Family S01E12 (1080p) [ABCD1234].mkv");
+    assert_eq!(info.titles, titles(&["Spy x Family"]));
+    assert_eq!(info.season, Some(1));
+    assert_eq!(info.episode, Some(12));
+    assert_eq!(info.resolution.as_deref(), Some("1080P"));
+
+    // Batches and years are not episodes.
+    let info = ReleaseInfo::parse("[VCB-Studio] Made in Abyss [01-13][2017][Ma10p_1080p]");
+    assert_eq!(info.episode, None);
+
+    let info = ReleaseInfo::parse("no brackets at all");
+    assert_eq!(info.group, None);
+    assert_eq!(info.titles, titles(&["no brackets at all"]));
+}
@@ -0,0 +1,66 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use anyhow::anyhow;
+use backon::BackoffBuilder;
+use blooming::is_retryable;
+use blooming::BackoffKind;
+use blooming::RetryConfig;
+use blooming::RetryPolicy;
+
+#[test]
+fn test_retry_policy() {
+    let constant = RetryPolicy::new(&RetryConfig {
+        delay: Duration::from_millis(100),
+        max_times: 2,
+        ..Default::default()
+    });
+    assert_eq!(constant.build().collect::<Vec<_>>(), vec![
+        Duration::from_millis(100),
+        Duration::from_millis(100),
+    ]);
+
+    let exponential = RetryPolicy::new(&RetryConfig {
+        backoff: BackoffKind::Exponential,
+        delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(250),
+        max_times: 4,
+        ..Default::default()
+    });
+    let delays = exponential
+        .build()
+        .map(|d| d.as_millis())
+        .collect::<Vec<_>>();
+    assert_eq!(delays, vec![100, 200, 250, 250]);
+
+    let disabled = RetryPolicy::new(&RetryConfig {
+        max_times: 0,
+        ..Default::default()
+    });
+    assert_eq!(disabled.build().next(), None);
+}
+
+#[tokio::test]
+async fn test_is_retryable() {
+    // Nothing listens on port 1.
+    let err = reqwest::get("http://127.0.0.1:1/").await.unwrap_err();
+    assert!(is_retryable(&err.into()));
+
+    let err = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+    assert!(is_retryable(&anyhow::Error::from(err).context("pull feed")));
+
+    assert!(!is_retryable(&anyhow!("Invalid RSS")));
+}
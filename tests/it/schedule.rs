@@ -0,0 +1,289 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use blooming::metrics::metrics;
+use blooming::AdaptiveConfig;
+use blooming::FeedHints;
+use blooming::Result;
+use blooming::Schedule;
+use blooming::ScheduleConfig;
+use blooming::WindowConfig;
+use chrono::DateTime;
+use chrono::Local;
+use chrono::NaiveDate;
+use chrono::TimeZone;
+use chrono::Utc;
+use chrono::Weekday;
+
+fn at(day: u32, hour: u32, min: u32, sec: u32) -> DateTime<Local> {
+    // 2023-01-23 is a Monday.
+    let time = NaiveDate::from_ymd_opt(2023, 1, day)
+        .and_then(|d| d.and_hms_opt(hour, min, sec))
+        .unwrap();
+    Local.from_local_datetime(&time).unwrap()
+}
+
+fn window(days: &[&str], start: &str, end: &str, interval: u64) -> WindowConfig {
+    WindowConfig {
+        days: days.iter().map(|d| d.to_string()).collect(),
+        start: start.to_string(),
+        end: end.to_string(),
+        interval: Duration::from_secs(interval),
+    }
+}
+
+#[test]
+fn test_schedule_windows() -> Result<()> {
+    let schedule = Schedule::new(Duration::from_secs(1800), &ScheduleConfig {
+        windows: vec![
+            window(&["Mon"], "18:00", "23:30", 120),
+            window(&["Fri"], "22:00", "02:00", 60),
+        ],
+        ..Default::default()
+    })?;
+
+    // In the window.
+    assert_eq!(
+        schedule.delay_at(at(23, 19, 0, 0)),
+        Duration::from_secs(120)
+    );
+    // Do not sleep over the start of the window.
+    assert_eq!(
+        schedule.delay_at(at(23, 17, 50, 0)),
+        Duration::from_secs(600)
+    );
+    assert_eq!(
+        schedule.delay_at(at(23, 12, 0, 0)),
+        Duration::from_secs(1800)
+    );
+    // Not the day.
+    assert_eq!(
+        schedule.delay_at(at(24, 19, 0, 0)),
+        Duration::from_secs(1800)
+    );
+    // Crossing midnight.
+    assert_eq!(
+        schedule.interval_at(at(27, 23, 0, 0)),
+        Duration::from_secs(60)
+    );
+    assert_eq!(
+        schedule.interval_at(at(28, 1, 0, 0)),
+        Duration::from_secs(60)
+    );
+    assert_eq!(
+        schedule.interval_at(at(28, 3, 0, 0)),
+        Duration::from_secs(1800)
+    );
+    assert_eq!(
+        schedule.interval_at(at(27, 1, 0, 0)),
+        Duration::from_secs(1800)
+    );
+
+    assert!(!schedule.immediate());
+
+    Ok(())
+}
+
+#[test]
+fn test_schedule_cron_and_jitter() -> Result<()> {
+    let schedule = Schedule::new(Duration::from_secs(1800), &ScheduleConfig {
+        cron: Some("0 */5 * * * *".to_string()),
+        jitter: Duration::from_secs(30),
+        immediate: true,
+        ..Default::default()
+    })?;
+    let now = at(23, 12, 3, 30);
+    assert_eq!(schedule.delay_at(now), Duration::from_secs(90));
+    for _ in 0..10 {
+        let delay = schedule.next_delay(now, None, None);
+        assert!(delay >= Duration::from_secs(90) && delay <= Duration::from_secs(120));
+    }
+    // Rate limited feeds postpone the poll, but never bring it forward.
+    let limited = now + chrono::Duration::seconds(600);
+    let delay = schedule.next_delay(now, None, Some(limited));
+    assert!(delay >= Duration::from_secs(600) && delay <= Duration::from_secs(630));
+    let limited = now + chrono::Duration::seconds(10);
+    let delay = schedule.next_delay(now, None, Some(limited));
+    assert!(delay >= Duration::from_secs(90));
+    assert!(schedule.immediate());
+
+    let invalid = [
+        ScheduleConfig {
+            cron: Some("every 5 minutes".to_string()),
+            ..Default::default()
+        },
+        ScheduleConfig {
+            windows: vec![window(&["Someday"], "18:00", "23:30", 120)],
+            ..Default::default()
+        },
+        ScheduleConfig {
+            windows: vec![window(&[], "18:00", "25:00", 120)],
+            ..Default::default()
+        },
+    ];
+    for config in invalid {
+        assert!(Schedule::new(Duration::from_secs(1800), &config).is_err());
+    }
+    let ended = ScheduleConfig {
+        cron: Some("0 0 0 1 1 * 2020".to_string()),
+        ..Default::default()
+    };
+    let err = Schedule::new(Duration::ZERO, &ended).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Cron expression '0 0 0 1 1 * 2020' has no upcoming time"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_feed_hints() -> Result<()> {
+    let byrbt = FeedHints::parse(&std::fs::read("tests/it/testdata/byrbt.xml")?);
+    assert_eq!(byrbt.ttl, Some(Duration::from_secs(3600)));
+    assert!(byrbt.skip_hours.is_empty());
+
+    let content = br#"<?xml version="1.0"?><rss version="2.0"><channel>
+        <title>t</title><link>l</link><description>d</description><ttl>10</ttl>
+        <skipHours><hour>1</hour><hour>2</hour><hour>24</hour></skipHours>
+        <skipDays><day>Sunday</day></skipDays>
+        </channel></rss>"#;
+    let hints = FeedHints::parse(content);
+    assert_eq!(hints.ttl, Some(Duration::from_secs(600)));
+    assert_eq!(hints.skip_hours, [0, 1, 2].into());
+    assert_eq!(hints.skip_days, [Weekday::Sun].into());
+
+    // The longest ttl, and only what every feed skips.
+    let merged = FeedHints::merge(&[hints, FeedHints {
+        ttl: Some(Duration::from_secs(60)),
+        skip_hours: [2, 3].into(),
+        ..Default::default()
+    }]);
+    assert_eq!(merged.ttl, Some(Duration::from_secs(600)));
+    assert_eq!(merged.skip_hours, [2].into());
+    assert!(merged.skip_days.is_empty());
+
+    // A huge ttl saturates instead of overflowing.
+    let content = br#"<?xml version="1.0"?><rss version="2.0"><channel>
+        <title>t</title><link>l</link><description>d</description>
+        <ttl>18446744073709551615</ttl>
+        </channel></rss>"#;
+    let hints = FeedHints::parse(content);
+    assert_eq!(hints.ttl, Some(Duration::from_secs(u64::MAX)));
+
+    Ok(())
+}
+
+#[test]
+fn test_adaptive_interval() -> Result<()> {
+    let config = |adaptive| ScheduleConfig {
+        adaptive,
+        ..Default::default()
+    };
+    let schedule = Schedule::new(
+        Duration::from_secs(600),
+        &config(Some(AdaptiveConfig::default())),
+    )?;
+    let mut adaptive = schedule.adaptive().unwrap();
+    assert_eq!(adaptive.current(), Duration::from_secs(600));
+
+    // Not enough items to estimate the publish rate.
+    let t0 = Local::now();
+    let at = |mins| t0 + chrono::Duration::minutes(mins);
+    let hints = FeedHints::default();
+    assert_eq!(
+        adaptive.update(&[at(0)], hints.clone(), at(0)).as_secs(),
+        600
+    );
+
+    // Follow the gaps between the items, a burst does not reset the interval.
+    let intervals = [
+        adaptive.update(&[at(0), at(10), at(20)], hints.clone(), at(20)),
+        adaptive.update(&[at(20), at(22)], hints.clone(), at(22)),
+        adaptive.update(&[at(24), at(22)], hints.clone(), at(24)),
+        adaptive.update(&[at(26)], hints.clone(), at(26)),
+    ];
+    let intervals = intervals.map(|d| d.as_secs());
+    assert_eq!(intervals, [600, 480, 390, 322]);
+
+    // Back off while the feed stays quiet.
+    assert_eq!(adaptive.update(&[], hints.clone(), at(30)).as_secs(), 322);
+    assert_eq!(adaptive.update(&[], hints.clone(), at(46)).as_secs(), 1200);
+    assert_eq!(adaptive.update(&[], hints.clone(), at(600)).as_secs(), 3600);
+
+    // Never faster than the ttl or the min, never slower than the max.
+    let burst = (0..60)
+        .map(|i| at(600) + chrono::Duration::seconds(i))
+        .collect::<Vec<_>>();
+    assert_eq!(adaptive.update(&burst, hints, at(601)).as_secs(), 60);
+    let ttl = FeedHints {
+        ttl: Some(Duration::from_secs(1800)),
+        ..Default::default()
+    };
+    assert_eq!(adaptive.update(&[], ttl, at(601)).as_secs(), 1800);
+    let huge = FeedHints {
+        ttl: Some(Duration::from_secs(u64::MAX)),
+        ..Default::default()
+    };
+    assert_eq!(adaptive.update(&[], huge, at(601)).as_secs(), 3600);
+
+    // Postpone out of the skipped hours (UTC).
+    let mut adaptive = schedule.adaptive().unwrap();
+    adaptive.update(
+        &[],
+        FeedHints {
+            skip_hours: [10, 11].into(),
+            ..Default::default()
+        },
+        Local::now(),
+    );
+    let now = Utc
+        .from_utc_datetime(
+            &NaiveDate::from_ymd_opt(2023, 1, 23)
+                .and_then(|d| d.and_hms_opt(9, 50, 0))
+                .unwrap(),
+        )
+        .with_timezone(&Local);
+    // 09:50 + 600s is in 10:xx, postponed to 12:00.
+    assert_eq!(adaptive.delay_at(now), Duration::from_secs(7800));
+
+    // Disabled without the config.
+    let fixed = Schedule::new(Duration::from_secs(600), &config(None))?;
+    assert!(fixed.adaptive().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_metrics() {
+    let m = metrics();
+    m.set_gauge(
+        "test_interval_seconds",
+        "Test.",
+        &[("source", "Mikan")],
+        60.0,
+    );
+    m.set_gauge("test_interval_seconds", "Test.", &[("source", "a\"b")], 1.5);
+    assert_eq!(
+        m.get("test_interval_seconds", &[("source", "Mikan")]),
+        Some(60.0)
+    );
+
+    let text = m.render();
+    assert!(text.contains("# TYPE test_interval_seconds gauge\n"));
+    assert!(text.contains("test_interval_seconds{source=\"Mikan\"} 60\n"));
+    assert!(text.contains("test_interval_seconds{source=\"a\\\"b\"} 1.5\n"));
+}
@@ -0,0 +1,140 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use blooming::release::ReleaseInfo;
+use blooming::state::format_episodes;
+use blooming::state::EpisodeEvent;
+use blooming::state::EpisodeLedger;
+use blooming::state::StateStore;
+use blooming::Result;
+use blooming::SeriesConfig;
+use chrono::Local;
+
+fn release(episode: u32) -> ReleaseInfo {
+    ReleaseInfo::parse(&format!(
+        "【豌豆字幕组】[海盗战记 / 冰海战记 第二季 / Vinland_Saga_S2][{episode:02}][简体][1080P][MP4]"
+    ))
+}
+
+#[test]
+fn test_episode_ledger() {
+    let expected = vec![SeriesConfig {
+        name: "Vinland Saga".to_string(),
+        season: Some(2),
+        episodes: 5,
+    }];
+    let mut ledger = EpisodeLedger::default();
+
+    assert!(ledger.observe("QQ", [&release(2)], &expected).is_empty());
+    assert!(ledger.observe("QQ", [&release(3)], &expected).is_empty());
+    // Another group with a different name of the same series.
+    let ani = ReleaseInfo::parse("[ANi] Vinland Saga Season 2 - 03 [1080P][CHT][MP4]");
+    assert!(ledger.observe("QQ", [&ani], &expected).is_empty());
+    assert_eq!(ledger.series.len(), 1);
+    // Series not configured are not recorded.
+    let other = ReleaseInfo::parse("[ANi] Oshi no Ko - 03 [1080P][CHT][MP4]");
+    assert!(ledger.observe("QQ", [&other], &expected).is_empty());
+    assert_eq!(ledger.series.len(), 1);
+
+    // Not complete while episode 4 is missing.
+    let events = ledger.observe("QQ", [&release(5)], &expected);
+    assert_eq!(events, vec![EpisodeEvent::Missing {
+        series: "海盗战记 S2".to_string(),
+        episodes: vec![4],
+        latest: 5,
+    }]);
+    assert_eq!(
+        events[0].to_string(),
+        "海盗战记 S2: episode 4 missing (latest: 5)"
+    );
+
+    // Alerts are sent once for each notifier.
+    assert!(ledger.observe("QQ", [&release(5)], &expected).is_empty());
+    assert_eq!(
+        ledger.observe("QQ Guild", [&release(5)], &expected).len(),
+        1
+    );
+
+    let events = ledger.observe("QQ", [&release(4)], &expected);
+    assert_eq!(events, vec![EpisodeEvent::Complete {
+        series: "海盗战记 S2".to_string(),
+        episodes: 5,
+    }]);
+    let record = &ledger.search("vinland")[0];
+    assert!(record.missing().is_empty());
+    assert_eq!(record.expected(&expected), Some(5));
+    assert_eq!(format_episodes(record.episodes.keys().copied()), "2-5");
+
+    // Dropped once removed from the config.
+    assert!(ledger.observe("QQ", [&release(6)], &[]).is_empty());
+    assert!(ledger.series.is_empty());
+}
+
+#[test]
+fn test_episode_ledger_batch() {
+    let expected = vec![SeriesConfig {
+        name: "Vinland Saga".to_string(),
+        season: Some(2),
+        episodes: 4,
+    }];
+    let mut ledger = EpisodeLedger::default();
+    assert!(ledger.observe("QQ", [&release(2)], &expected).is_empty());
+
+    // Feeds list the latest first, episode 3 is not missing.
+    let events = ledger.observe("QQ", [&release(4), &release(3)], &expected);
+    assert_eq!(events, vec![EpisodeEvent::Complete {
+        series: "海盗战记 S2".to_string(),
+        episodes: 4,
+    }]);
+
+    // A gap in the batch is still missing.
+    let mut ledger = EpisodeLedger::default();
+    let events = ledger.observe("QQ", [&release(4), &release(2)], &expected);
+    assert_eq!(events, vec![EpisodeEvent::Missing {
+        series: "海盗战记 S2".to_string(),
+        episodes: vec![3],
+        latest: 4,
+    }]);
+}
+
+#[test]
+fn test_state_store() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("blooming-state-{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    let now = Local::now();
+    let state = StateStore::open(Some(path))?;
+    let expected = [SeriesConfig {
+        name: "Vinland Saga".to_string(),
+        ..Default::default()
+    }];
+    state.update(|state| state.ledger.observe("QQ", [&release(1)], &expected))?;
+    state.update(|state| state.cursors.insert("nyaa/qq".to_string(), now))?;
+
+    let state = StateStore::open(Some(path))?;
+    assert_eq!(state.read(|state| state.ledger.series.len()), 1);
+    assert_eq!(state.read(|state| state.cursors["nyaa/qq"]), now);
+
+    // Changes to a snapshot are not persisted.
+    let snapshot = StateStore::snapshot(Some(path))?;
+    assert_eq!(snapshot.read(|state| state.cursors.len()), 1);
+    snapshot.update(|state| state.cursors.clear())?;
+    assert!(snapshot.read(|state| state.cursors.is_empty()));
+    let state = StateStore::open(Some(path))?;
+    assert_eq!(state.read(|state| state.cursors.len()), 1);
+
+    std::fs::remove_file(path)?;
+    assert_eq!(format_episodes([1, 2, 3, 5, 7, 8]), "1-3, 5, 7-8");
+    Ok(())
+}
@@ -0,0 +1,112 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use blooming::metrics::metrics;
+use blooming::supervisor::Supervisor;
+use blooming::BackoffKind;
+use blooming::RetryConfig;
+use blooming::RetryPolicy;
+use tokio_util::sync::CancellationToken;
+
+#[tokio::test]
+async fn test_supervisor() {
+    let supervisor = Supervisor::new(RetryPolicy::new(&RetryConfig {
+        backoff: BackoffKind::Constant,
+        max_times: usize::MAX,
+        delay: Duration::from_millis(10),
+        ..Default::default()
+    }));
+    let token = CancellationToken::new();
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    // Panics twice, then runs until cancelled.
+    let handle = supervisor.spawn("nyaa/qq".to_string(), token.clone(), {
+        let runs = runs.clone();
+        let token = token.clone();
+        move || {
+            let runs = runs.clone();
+            let token = token.clone();
+            async move {
+                if runs.fetch_add(1, Ordering::SeqCst) < 2 {
+                    panic!("parse failed");
+                }
+                token.cancelled().await;
+            }
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let health = &supervisor.health()["nyaa/qq"];
+    assert!(health.running);
+    assert_eq!(health.restarts, 2);
+    assert_eq!(health.last_error.as_deref(), Some("panicked: parse failed"));
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+    assert_eq!(
+        metrics().get("blooming_task_restarts_total", &[("task", "nyaa/qq")]),
+        Some(2.0)
+    );
+    assert_eq!(
+        metrics().get("blooming_task_up", &[("task", "nyaa/qq")]),
+        Some(1.0)
+    );
+
+    token.cancel();
+    tokio::time::timeout(Duration::from_secs(1), handle)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+    supervisor.remove("nyaa/qq");
+    assert!(supervisor.health().is_empty());
+    assert_eq!(
+        metrics().get("blooming_task_up", &[("task", "nyaa/qq")]),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_supervisor_abort() {
+    let supervisor = Supervisor::default();
+    let stopped = Arc::new(AtomicUsize::new(0));
+
+    struct Guard(Arc<AtomicUsize>);
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let handle = supervisor.spawn("dmhy/qq".to_string(), CancellationToken::new(), {
+        let stopped = stopped.clone();
+        move || {
+            let guard = Guard(stopped.clone());
+            async move {
+                let _guard = guard;
+                std::future::pending::<()>().await;
+            }
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // Aborting the supervisor aborts the task too.
+    handle.abort();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(stopped.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,155 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use blooming::torrent::decode;
+use blooming::torrent::TorrentFile;
+use blooming::torrent::TorrentInfo;
+use blooming::torrent::Value;
+use blooming::Result;
+
+#[test]
+fn test_decode_bencode() -> Result<()> {
+    let value = decode(b"d3:bar4:spam3:fooi42e4:listl1:ai-1eee")?;
+    assert_eq!(value.get("bar"), Some(&Value::Bytes(b"spam".to_vec())));
+    assert_eq!(value.get("foo").and_then(Value::as_int), Some(42));
+    assert_eq!(
+        value.get("list"),
+        Some(&Value::List(vec![
+            Value::Bytes(b"a".to_vec()),
+            Value::Int(-1)
+        ]))
+    );
+
+    assert!(decode(b"d3:bar").is_err());
+    assert!(decode(b"i1ei2e").is_err());
+    assert!(decode(b"5:abc").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_decode_deep_bencode() -> Result<()> {
+    let nested = |depth: usize| [vec![b'l'; depth], vec![b'e'; depth]].concat();
+    assert!(decode(&nested(64)).is_ok());
+    let err = decode(&nested(65)).unwrap_err();
+    assert!(err.to_string().contains("nested deeper than 64"));
+    // Would overflow the stack without the limit.
+    assert!(decode(&nested(1_000_000)).is_err());
+
+    let torrent = [&b"d4:info"[..], &nested(1_000_000), b"e"].concat();
+    assert!(TorrentInfo::parse(&torrent).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_torrent_negative_length() {
+    let single = b"d4:infod6:lengthi-1e4:name1:a12:piece lengthi16384e6:pieces0:ee";
+    let err = TorrentInfo::parse(single).unwrap_err();
+    assert_eq!(err.to_string(), "Negative length -1 in torrent");
+
+    let multi =
+        b"d4:infod5:filesld6:lengthi-5e4:pathl1:aeee4:name1:a12:piece lengthi16384e6:pieces0:ee";
+    assert!(TorrentInfo::parse(multi).is_err());
+
+    let piece = b"d4:infod6:lengthi1e4:name1:a12:piece lengthi-1e6:pieces0:ee";
+    assert!(TorrentInfo::parse(piece).is_err());
+}
+
+#[test]
+fn test_parse_torrent_v1() -> Result<()> {
+    let info = TorrentInfo::parse(&std::fs::read("tests/it/testdata/v1.torrent")?)?;
+
+    let expected = TorrentInfo {
+        info_hash_v1: Some("724780110db8b01f992d9be5aacb93389f0d2888".to_string()),
+        info_hash_v2: None,
+        name: "[ANi] Vampire Dies S2 - 03".to_string(),
+        files: vec![
+            TorrentFile {
+                path: "[ANi] Vampire Dies S2 - 03.mp4".to_string(),
+                length: 580019840,
+            },
+            TorrentFile {
+                path: "Fonts/font.ttf".to_string(),
+                length: 2048,
+            },
+        ],
+        piece_length: 262144,
+        total_size: 580021888,
+        trackers: vec![
+            "http://t.nyacat.org/announce".to_string(),
+            "udp://tracker.opentrackr.org:1337/announce".to_string(),
+        ],
+        private: false,
+    };
+    assert_eq!(info, expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_torrent_hybrid() -> Result<()> {
+    let info = TorrentInfo::parse(&std::fs::read("tests/it/testdata/hybrid.torrent")?)?;
+
+    assert_eq!(
+        info.info_hash_v1.as_deref(),
+        Some("688e283386b7a865b1e7d805bf5ecdadbd0e39f4")
+    );
+    assert_eq!(
+        info.info_hash_v2.as_deref(),
+        Some("f992b1c8bb9e5a208de9285c3928906138f41221e345154cf2744fe802544680")
+    );
+    assert_eq!(info.name, "Vinland Saga S2");
+    // The padding file is skipped.
+    assert_eq!(info.files.len(), 2);
+    assert_eq!(info.total_size, 454819940);
+    assert_eq!(info.piece_length, 4194304);
+    assert!(!info.private);
+
+    Ok(())
+}
+
+#[test]
+fn test_magnet() -> Result<()> {
+    let info = TorrentInfo::parse(&std::fs::read("tests/it/testdata/v1.torrent")?)?;
+    assert_eq!(
+        info.magnet().unwrap(),
+        "magnet:?xt=urn:btih:724780110db8b01f992d9be5aacb93389f0d2888\
+        &dn=%5BANi%5D%20Vampire%20Dies%20S2%20-%2003\
+        &tr=http%3A%2F%2Ft.nyacat.org%2Fannounce\
+        &tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337%2Fannounce"
+    );
+
+    // Trackers of private torrents contain passkeys.
+    let info = TorrentInfo::parse(&std::fs::read("tests/it/testdata/private.torrent")?)?;
+    assert!(info.private);
+    let magnet = info.magnet().unwrap();
+    assert_eq!(
+        magnet,
+        "magnet:?xt=urn:btih:6604d6b87f645c96f6ba5d525855d158a5f1cf12\
+        &xt=urn:btmh:12203afb64436bb9f7c79cd1011559657554da9cf01181c35e3a6534d4e2c67fdc86\
+        &dn=Vinland%20Saga%20S2"
+    );
+
+    let info = TorrentInfo {
+        private: true,
+        ..TorrentInfo::parse(&std::fs::read("tests/it/testdata/v1.torrent")?)?
+    };
+    assert!(!info.magnet().unwrap().contains("tr="));
+
+    let info = TorrentInfo::default();
+    assert!(info.magnet().is_none());
+
+    Ok(())
+}
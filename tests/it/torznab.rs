@@ -0,0 +1,176 @@
+// Copyright 2023 RinChanNOWWW
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::File;
+use std::io::BufReader;
+
+use blooming::register;
+use blooming::Config;
+use blooming::Result;
+use blooming::SourceFactory;
+use blooming::Torznab;
+use blooming::TorznabConfig;
+use blooming::TorznabMode;
+
+use crate::notifier::serve_http;
+
+#[test]
+fn test_parse_torznab() -> Result<()> {
+    let file = File::open("tests/it/testdata/torznab.xml")?;
+    let items = Torznab::parse_items(BufReader::new(file))?;
+    assert_eq!(items.len(), 2);
+
+    let item = &items[0];
+    assert_eq!(item.url, "https://nyaa.si/view/1631820");
+    assert_eq!(
+        item.enclosure.as_deref(),
+        Some("http://127.0.0.1:9117/dl/nyaasi/?jackett_apikey=secret&path=abc&file=Vinland")
+    );
+    assert_eq!(item.size, Some(1503238553));
+    assert_eq!(item.seeders, Some(1523));
+    assert_eq!(item.leechers, Some(42));
+    assert_eq!(
+        item.info_hash.as_deref(),
+        Some("5dd79686d9b6c1ab2a6091363d493d05333d8899")
+    );
+    assert_eq!(item.category.as_deref(), Some("5070"));
+    assert_eq!(item.attrs["imdbid"], "10233448");
+    assert_eq!(item.attrs["category"], "5070,100001");
+    assert_eq!(item.magnet, None);
+
+    // Magnet only.
+    let item = &items[1];
+    assert_eq!(item.enclosure, None);
+    assert_eq!(item.size, Some(863502336));
+    assert_eq!(item.leechers, Some(12));
+    assert_eq!(
+        item.magnet().as_deref(),
+        Some("magnet:?xt=urn:btih:fa2fca2b18dc4d6e166cab56fd36dcb547eafe6e&dn=Vampire")
+    );
+
+    let error = br#"<?xml version="1.0" encoding="UTF-8"?><error code="100" description="Invalid API Key" />"#;
+    let err = Torznab::parse_items(&error[..]).unwrap_err();
+    assert_eq!(err.to_string(), "Torznab error 100: Invalid API Key");
+
+    Ok(())
+}
+
+#[test]
+fn test_magnet_passkey() -> Result<()> {
+    let content = br#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:torznab="http://torznab.com/schemas/2015/feed">
+  <channel>
+    <item>
+      <title>[SubsPlease] Vinland Saga S2 - 03 (1080p)</title>
+      <pubDate>Tue, 24 Jan 2023 13:29:39 +0000</pubDate>
+      <link>magnet:?xt=urn:btih:fa2fca2b18dc4d6e166cab56fd36dcb547eafe6e&amp;tr=https%3A%2F%2Ftracker.example%2Fannounce.php%3Fpasskey%3Dsecret</link>
+    </item>
+    <item>
+      <title>[SubsPlease] Vinland Saga S2 - 04 (1080p)</title>
+      <pubDate>Tue, 31 Jan 2023 13:29:39 +0000</pubDate>
+      <link>https://private.example/details/2</link>
+      <torznab:attr name="magneturl" value="magnet:?xt=urn:btih:5dd79686d9b6c1ab2a6091363d493d05333d8899&amp;dn=Vinland&amp;tr=https://tracker.example/secret/announce&amp;tr=udp://tracker.example:80" />
+    </item>
+  </channel>
+</rss>"#;
+    let items = Torznab::parse_items(&content[..])?;
+    assert_eq!(
+        items[0].magnet().as_deref(),
+        Some("magnet:?xt=urn:btih:fa2fca2b18dc4d6e166cab56fd36dcb547eafe6e")
+    );
+    assert_eq!(
+        items[1].magnet().as_deref(),
+        Some("magnet:?xt=urn:btih:5dd79686d9b6c1ab2a6091363d493d05333d8899&dn=Vinland")
+    );
+    assert!(
+        items
+            .iter()
+            .all(|item| !item.magnet().unwrap().contains("secret"))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_torznab_url() -> Result<()> {
+    let content = br#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:torznab="http://torznab.com/schemas/2015/feed">
+  <channel>
+    <item>
+      <title>[SubsPlease] Vinland Saga S2 - 03 (1080p)</title>
+      <pubDate>Tue, 24 Jan 2023 13:29:39 +0000</pubDate>
+      <link>http://127.0.0.1:9696/1/download?apikey=secret&amp;link=abc</link>
+    </item>
+    <item>
+      <title>[SubsPlease] Vinland Saga S2 - 04 (1080p)</title>
+      <pubDate>Tue, 31 Jan 2023 13:29:39 +0000</pubDate>
+      <guid>http://127.0.0.1:9117/dl/nyaasi/?jackett_apikey=secret&amp;path=abc</guid>
+      <link>http://127.0.0.1:9117/dl/nyaasi/?jackett_apikey=secret&amp;path=abc</link>
+    </item>
+    <item>
+      <title>[SubsPlease] Vinland Saga S2 - 05 (1080p)</title>
+      <pubDate>Tue, 07 Feb 2023 13:29:39 +0000</pubDate>
+      <guid>https://nyaa.si/view/1640000</guid>
+      <link>http://127.0.0.1:9117/dl/nyaasi/?jackett_apikey=secret&amp;path=def</link>
+    </item>
+  </channel>
+</rss>"#;
+    let items = Torznab::parse_items(&content[..])?;
+
+    // Download links with the api key are never the url, which is sent out.
+    assert_eq!(items[0].url, "");
+    assert_eq!(items[1].url, "");
+    assert_eq!(items[2].url, "https://nyaa.si/view/1640000");
+    // They are still used to download the torrents.
+    assert!(items[0].torrent_url().contains("apikey=secret"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_torznab_source() -> Result<()> {
+    let feed = std::fs::read_to_string("tests/it/testdata/torznab.xml")?;
+    let (addr, requests) = serve_http(move |_, _| {
+        let headers = "Content-Type: application/rss+xml\r\n".to_string();
+        ("200 OK", headers, feed.clone())
+    })
+    .await?;
+
+    let config = Config {
+        torznab: Some(TorznabConfig {
+            url: format!("http://{addr}/api/v2.0/indexers/all/results/torznab/"),
+            apikey: "secret".into(),
+            mode: TorznabMode::TvSearch,
+            query: "vinland saga".to_string(),
+            categories: vec![5070, 5080],
+            season: Some(2),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut factory = SourceFactory::default();
+    register(&mut factory, &config)?;
+    let source = &factory.sources()[0];
+
+    let items = source.pull_items().await?;
+    assert_eq!(items.len(), 2);
+
+    let request = &requests.lock().unwrap()[0];
+    assert!(request.starts_with(
+        "GET /api/v2.0/indexers/all/results/torznab/api\
+        ?apikey=secret&t=tvsearch&q=vinland+saga&cat=5070%2C5080&season=2 HTTP/1.1"
+    ));
+
+    Ok(())
+}